@@ -8,9 +8,13 @@ use bevy::{
     },
 };
 use bevy_asset_loader::prelude::*;
+#[cfg(feature = "client")]
 use bevy_kira_audio::AudioSource;
 
-use crate::GameLoading;
+use crate::{
+    levels::manifest::LevelManifest, units::kinds::UnitKindManifest, weapons::WeaponManifest,
+    GameLoading,
+};
 
 #[derive(AssetCollection, Resource)]
 pub struct TextureAssets {
@@ -28,6 +32,14 @@ pub struct TextureAssets {
 
 #[derive(AssetCollection, Resource)]
 pub struct LevelAssets {
+    #[asset(path = "levels/manifest.levels.json")]
+    pub manifest: Handle<LevelManifest>,
+}
+
+// Level scenes are spawned by path from the level manifest, they are listed here so they're
+// loaded up front
+#[derive(AssetCollection, Resource)]
+pub struct LevelSceneAssets {
     // URBAN
     #[asset(path = "levels/urban/expurban_farawaybuildings.gltf#Scene0")]
     pub urban_far_away_buildings: Handle<Scene>,
@@ -159,6 +171,15 @@ pub struct LevelAssets {
     pub bfa_triggers: Handle<Scene>,
 }
 
+#[derive(AssetCollection, Resource)]
+pub struct UnitKindAssets {
+    #[asset(path = "units/kinds.units.json")]
+    pub manifest: Handle<UnitKindManifest>,
+}
+
+// Unit scenes and animations are loaded by path from the unit kinds, they are listed here so
+// they're loaded up front
+#[cfg(feature = "client")]
 #[derive(AssetCollection, Resource)]
 pub struct UnitAssets {
     #[asset(path = "units/unit1.gltf#Scene0")]
@@ -178,6 +199,14 @@ pub struct UnitAssets {
 }
 
 #[derive(AssetCollection, Resource)]
+pub struct WeaponAssets {
+    #[asset(path = "weapons/manifest.weapons.json")]
+    pub manifest: Handle<WeaponManifest>,
+}
+
+// Prop scenes are only visuals, the headless binary and tests insert defaults. Gun scenes are
+// spawned by path from the weapon manifest, they are listed here so they're loaded up front
+#[derive(AssetCollection, Resource, Default)]
 pub struct PropAssets {
     #[asset(path = "props/gun/expgun_gun.gltf#Scene0")]
     pub gun: Handle<Scene>,
@@ -196,6 +225,8 @@ pub struct PropAssets {
     #[asset(path = "props/projectile/crosshair.gltf#Scene0")]
     pub crosshair: Handle<Scene>,
 }
+
+#[cfg(feature = "client")]
 #[derive(AssetCollection, Resource)]
 pub struct AudioAssets {
     // PLAYER GUN
@@ -1,14 +1,130 @@
 use std::time::Duration;
 
 use bevy::prelude::*;
+use bevy_fps_controller::controller::RenderPlayer;
 use bevy_kira_audio::*;
+use rand::seq::SliceRandom;
+use rand_pcg::Pcg32;
 
-use crate::{assets::AudioAssets, levels::GameLevel, ui::AudioVolumes, GameLoading};
+use crate::{
+    assets::AudioAssets,
+    damage::{DamageSet, Damaged, Died},
+    levels::GameLevel,
+    pause::PauseState,
+    settings::AudioVolumes,
+    sfx::{PlaySfx, Sfx},
+    GameLoading,
+};
 
 pub struct GameAudioPlugin;
 impl Plugin for GameAudioPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(set_music.run_if(in_state(GameLoading::Loaded)));
+        app.init_resource::<SfxRng>().add_systems(
+            (
+                set_music,
+                damage_sfx.after(DamageSet),
+                play_sfx.after(damage_sfx),
+            )
+                .distributive_run_if(in_state(GameLoading::Loaded)),
+        );
+    }
+}
+
+/// Picks between the variations of each sound. Kept apart from GameRng so playing sounds
+/// doesn't change how the game plays out
+#[derive(Resource)]
+struct SfxRng(Pcg32);
+
+impl Default for SfxRng {
+    fn default() -> Self {
+        SfxRng(Pcg32::new(0x853c49e6748fea9b, 0xda3e39cb94b95bdb))
+    }
+}
+
+fn damage_sfx(
+    mut damaged_events: EventReader<Damaged>,
+    mut died_events: EventReader<Died>,
+    player: Query<(), With<RenderPlayer>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    for Damaged(damage) in damaged_events.iter() {
+        if player.contains(damage.target) {
+            sfx.send(PlaySfx(Sfx::PlayerHit));
+        }
+    }
+    for died in died_events.iter() {
+        if !player.contains(died.entity) {
+            sfx.send(PlaySfx(Sfx::EnemyExplode));
+        }
+    }
+}
+
+fn play_sfx(
+    mut events: EventReader<PlaySfx>,
+    audio: Res<bevy_kira_audio::Audio>,
+    audio_assets: Res<AudioAssets>,
+    audio_volumes: Res<AudioVolumes>,
+    mut rng: ResMut<SfxRng>,
+) {
+    for PlaySfx(sfx) in events.iter() {
+        let a = &audio_assets;
+        let (clips, rate, volume) = match sfx {
+            Sfx::PlayerGun => (
+                vec![
+                    &a.playergun1,
+                    &a.playergun2,
+                    &a.playergun3,
+                    &a.playergun4,
+                    &a.playergun5,
+                ],
+                1.0,
+                1.0,
+            ),
+            Sfx::EnemyGun => (
+                vec![
+                    &a.enemygun1,
+                    &a.enemygun2,
+                    &a.enemygun3,
+                    &a.enemygun4,
+                    &a.enemygun5,
+                ],
+                1.0,
+                1.2,
+            ),
+            Sfx::PlayerHit => (
+                vec![
+                    &a.playerhit1,
+                    &a.playerhit2,
+                    &a.playerhit3,
+                    &a.playerhit4,
+                    &a.playerhit5,
+                ],
+                1.8,
+                0.8,
+            ),
+            Sfx::EnemyExplode => (
+                vec![
+                    &a.enemyexplode1,
+                    &a.enemyexplode2,
+                    &a.enemyexplode3,
+                    &a.enemyexplode4,
+                    &a.enemyexplode5,
+                    &a.enemyexplode6,
+                ],
+                1.8,
+                1.1,
+            ),
+            // There are no reload or pickup recordings, so these are other sounds slowed down
+            // into a clack and sped up into a chirp
+            Sfx::Reload => (vec![&a.playergun1, &a.playergun3], 0.5, 0.4),
+            Sfx::Pickup => (vec![&a.playerhit2, &a.playerhit4], 3.0, 0.6),
+        };
+        if let Some(clip) = clips.choose(&mut rng.0) {
+            audio
+                .play((*clip).clone())
+                .with_playback_rate(rate)
+                .with_volume((audio_volumes.sfx * volume) as f64);
+        }
     }
 }
 
@@ -19,12 +135,18 @@ fn set_music(
     mut audio_instances: ResMut<Assets<AudioInstance>>,
     mut current_handle: Local<Option<Handle<AudioInstance>>>,
     audio_volumes: Res<AudioVolumes>,
+    pause_state: Res<State<PauseState>>,
 ) {
-    if audio_volumes.is_changed() {
+    // Duck the music while the menu is open
+    let duck = match pause_state.0 {
+        PauseState::Running => 1.0,
+        PauseState::Paused => 0.3,
+    };
+    if audio_volumes.is_changed() || pause_state.is_changed() {
         if let Some(current_handle) = &*current_handle {
             if let Some(instance) = audio_instances.get_mut(current_handle) {
                 instance.set_volume(
-                    audio_volumes.music as f64 * 0.6,
+                    audio_volumes.music as f64 * 0.6 * duck,
                     AudioTween::linear(Duration::from_secs_f32(0.1)),
                 );
             }
@@ -63,7 +185,7 @@ fn set_music(
                 Duration::from_secs_f32(0.5),
                 AudioEasing::OutPowi(2),
             ))
-            .with_volume(audio_volumes.music as f64 * 0.6)
+            .with_volume(audio_volumes.music as f64 * 0.6 * duck)
             .looped()
             .handle(),
     );
@@ -0,0 +1,149 @@
+//! Runs gameplay without a window, renderer or audio, stepping at the fixed timestep.
+//!
+//! `traverse-headless [--level <GameLevel>] [--seconds <N>]`
+
+use std::time::Duration;
+
+use bevy::{
+    animation::AnimationPlugin,
+    app::{AppExit, ScheduleRunnerSettings},
+    gltf::GltfPlugin,
+    input::InputPlugin,
+    log::LogPlugin,
+    pbr::CubemapVisibleEntities,
+    prelude::*,
+    render::{
+        mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+        primitives::{Aabb, CubemapFrusta, Frustum},
+        view::VisibleEntities,
+    },
+    scene::ScenePlugin,
+};
+use bevy_asset_loader::prelude::{LoadingState, LoadingStateAppExt};
+use bevy_fps_controller::controller::FpsController;
+use iyes_progress::ProgressPlugin;
+use traverse::{
+    assets::{LevelAssets, LevelSceneAssets, PropAssets, UnitKindAssets, WeaponAssets},
+    character_controller::spawn_player,
+    levels::GameLevel,
+    player::{RunStats, TeleportPlayer},
+    settings::{Settings, SettingsPlugin},
+    AppState, GameLoading, GameplayPlugin, TIMESTEP,
+};
+
+/// From the command line
+#[derive(Resource)]
+struct HeadlessArgs {
+    level: GameLevel,
+    /// Exit after this long, otherwise run until killed
+    seconds: Option<f32>,
+}
+
+impl HeadlessArgs {
+    fn parse() -> HeadlessArgs {
+        let mut args = HeadlessArgs {
+            level: GameLevel::Kitchen,
+            seconds: None,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let value = iter.next().unwrap_or_default();
+            match arg.as_str() {
+                "--level" => match serde_json::from_str(&format!("\"{value}\"")) {
+                    Ok(level) => args.level = level,
+                    Err(e) => warn!("Ignoring unknown level {value}: {e}"),
+                },
+                "--seconds" => match value.parse() {
+                    Ok(seconds) => args.seconds = Some(seconds),
+                    Err(e) => warn!("Ignoring --seconds {value}: {e}"),
+                },
+                _ => warn!("Ignoring unknown argument {arg}"),
+            }
+        }
+        args
+    }
+}
+
+fn main() {
+    let mut app = App::new();
+
+    app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f32(
+        TIMESTEP,
+    )))
+    .add_plugins(MinimalPlugins)
+    .add_plugin(LogPlugin::default())
+    .add_plugin(TransformPlugin)
+    .add_plugin(HierarchyPlugin)
+    .add_plugin(InputPlugin)
+    .add_plugin(AssetPlugin::default())
+    .add_plugin(ScenePlugin)
+    .add_plugin(AnimationPlugin::default())
+    // Level and unit scenes are loaded from gltf. Without the render plugins, the asset types
+    // and components they're made of are added here
+    .add_asset::<Mesh>()
+    .add_asset::<Image>()
+    .add_asset::<StandardMaterial>()
+    .add_asset::<SkinnedMeshInverseBindposes>()
+    .add_plugin(GltfPlugin)
+    .register_type::<Visibility>()
+    .register_type::<ComputedVisibility>()
+    .register_type::<Aabb>()
+    .register_type::<SkinnedMesh>()
+    .register_type::<PointLight>()
+    .register_type::<CubemapVisibleEntities>()
+    .register_type::<CubemapFrusta>()
+    .register_type::<SpotLight>()
+    .register_type::<VisibleEntities>()
+    .register_type::<Frustum>()
+    .insert_resource(HeadlessArgs::parse())
+    // Adds the states, so before the loading state
+    .add_plugin(GameplayPlugin)
+    .add_loading_state(LoadingState::new(GameLoading::AssetLoading))
+    .add_plugin(ProgressPlugin::new(GameLoading::AssetLoading).continue_to(GameLoading::Loaded))
+    .add_collection_to_loading_state::<_, LevelAssets>(GameLoading::AssetLoading)
+    .add_collection_to_loading_state::<_, LevelSceneAssets>(GameLoading::AssetLoading)
+    .add_collection_to_loading_state::<_, UnitKindAssets>(GameLoading::AssetLoading)
+    .add_collection_to_loading_state::<_, WeaponAssets>(GameLoading::AssetLoading)
+    .init_resource::<PropAssets>()
+    .add_plugin(SettingsPlugin)
+    .add_system(start.in_schedule(OnEnter(GameLoading::Loaded)))
+    .add_system(exit_after.run_if(in_state(GameLoading::Loaded)));
+
+    app.run();
+}
+
+fn start(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    args: Res<HeadlessArgs>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut teleport_events: EventWriter<TeleportPlayer>,
+) {
+    spawn_player(&mut commands, &settings);
+    commands.add(|world: &mut World| {
+        // There's no cursor to lock, input is enabled so the game isn't paused
+        for mut fps_controller in world.query::<&mut FpsController>().iter_mut(world) {
+            fps_controller.enable_input = true;
+        }
+    });
+    next_app_state.set(AppState::InGame);
+    teleport_events.send(TeleportPlayer(args.level.clone()));
+    info!("Running {:?} headless", args.level);
+}
+
+fn exit_after(
+    args: Res<HeadlessArgs>,
+    run_stats: Res<RunStats>,
+    time: Res<Time>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    if let Some(seconds) = args.seconds {
+        if time.elapsed_seconds() >= seconds {
+            info!(
+                "Done after {seconds}s: {} deaths, {} shots fired, {} hit",
+                run_stats.deaths, run_stats.shots_fired, run_stats.shots_hit
+            );
+            app_exit_events.send(AppExit);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::{
+    core_pipeline::{fxaa::Fxaa, tonemapping::Tonemapping},
+    math::vec2,
+    prelude::*,
+    window::CursorGrabMode,
+};
+use bevy_egui::EguiContexts;
+use bevy_fps_controller::controller::*;
+use bevy_polyline::prelude::{Polyline, PolylineBundle, PolylineMaterial};
+
+use crate::{
+    input::{Action, ActionState, Rebinding},
+    pause::SettingClock,
+    settings::Settings,
+    AppState, ClientSet,
+};
+
+use super::spawn_player;
+
+pub struct CharacterController;
+impl Plugin for CharacterController {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup).add_systems(
+            (
+                manage_cursor,
+                //display_text,
+            )
+                .in_set(ClientSet::Cursor)
+                .after(ClientSet::Ui),
+        );
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut polyline_materials: ResMut<Assets<PolylineMaterial>>,
+    mut polylines: ResMut<Assets<Polyline>>,
+    settings: Res<Settings>,
+) {
+    let polyline = polylines.add(Polyline {
+        vertices: vec![Vec3::ZERO, Vec3::ZERO],
+    });
+    commands.spawn_empty().insert(PolylineBundle {
+        polyline: polyline.clone(),
+        material: polyline_materials.add(PolylineMaterial {
+            width: 10.0,
+            color: Color::RED,
+            perspective: true,
+            depth_bias: 0.0,
+        }),
+        ..default()
+    });
+
+    let render_player = spawn_player(&mut commands, &settings);
+    commands
+        .entity(render_player)
+        .insert((
+            Visibility::default(),
+            ComputedVisibility::default(),
+            Camera3dBundle {
+                projection: Projection::Perspective(PerspectiveProjection {
+                    fov: settings.fov.to_radians(),
+                    ..default()
+                }),
+                camera: Camera {
+                    hdr: true,
+                    ..default()
+                },
+                tonemapping: Tonemapping::TonyMcMapface,
+                transform: Transform::from_xyz(0.0, 0.0, 0.0),
+                ..default()
+            },
+            FogSettings {
+                color: Color::rgba(0.1, 0.1, 0.1, 1.0),
+                falloff: FogFalloff::Exponential { density: 0.0003 },
+                ..default()
+            },
+        ))
+        .insert(EnvironmentMapLight {
+            diffuse_map: asset_server.load("environment_maps/pisa_diffuse_rgb9e5_zstd.ktx2"),
+            specular_map: asset_server.load("environment_maps/pisa_specular_rgb9e5_zstd.ktx2"),
+        })
+        .insert(Fxaa::default())
+        .insert(polyline);
+
+    commands.spawn(
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/fira_mono.ttf"),
+                font_size: 24.0,
+                color: Color::BLACK,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..default()
+            },
+            ..default()
+        }),
+    );
+}
+
+pub fn manage_cursor(
+    keys: Res<Input<KeyCode>>,
+    mut fps_controller: Query<&mut FpsController>,
+    action_state: Res<ActionState>,
+    rebinding: Res<Rebinding>,
+    app_state: Res<State<AppState>>,
+    //#[cfg(debug_assertions)] editor_state: Res<EditorState>,
+    mut windows: Query<&mut Window>,
+    mut contexts: EguiContexts,
+    setting_clock: Res<SettingClock>,
+) {
+    if contexts.ctx_mut().wants_pointer_input()
+        || setting_clock.0
+        || rebinding.0.is_some()
+        || app_state.0 != AppState::InGame
+    {
+        return;
+    }
+    let mut window = windows.single_mut();
+    let mut fps_controller = fps_controller.single_mut();
+    let cursor_locked = window.cursor.grab_mode == CursorGrabMode::Locked;
+    let mut lock = None;
+    if action_state.just_pressed(Action::Menu) {
+        lock = Some(!cursor_locked);
+    }
+    // Escape always unlocks, browsers release pointer lock on it regardless
+    if keys.just_pressed(KeyCode::Escape) || (!cursor_locked && fps_controller.enable_input) {
+        // Unlock
+        lock = Some(false);
+    }
+
+    #[allow(unused_assignments, unused_mut)]
+    let mut editor_active = false;
+
+    //#[cfg(debug_assertions)]
+    //{
+    //    editor_active = editor_state.active;
+    //}
+
+    if action_state.just_pressed(Action::Fire)
+        && (!fps_controller.enable_input || window.cursor.visible || !cursor_locked)
+        && !editor_active
+    {
+        // Lock
+        lock = Some(true);
+    }
+
+    if let Some(lock) = lock {
+        if lock {
+            // Lock
+            fps_controller.enable_input = true;
+            window.cursor.grab_mode = CursorGrabMode::Locked;
+            window.cursor.visible = false;
+        } else {
+            // Unlock
+            fps_controller.enable_input = false;
+            window.cursor.grab_mode = CursorGrabMode::None;
+            window.cursor.visible = true;
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    if cursor_locked {
+        let (w, h) = (window.width(), window.height());
+        window.set_cursor_position(Some(vec2(w / 2.0, h / 2.0)));
+    }
+}
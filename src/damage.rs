@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{pause::PauseState, GameLoading, Health};
+
+pub struct DamagePlugin;
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<Damaged>()
+            .add_event::<Died>()
+            .add_systems(
+                (tick_invulnerability, apply_damage)
+                    .chain()
+                    .in_set(DamageSet)
+                    .distributive_run_if(in_state(GameLoading::Loaded))
+                    .distributive_run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+/// Where damage events are applied. Systems sending them run before it, systems reading
+/// Damaged and Died after
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct DamageSet;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DamageKind {
+    /// The player's hitscan gun
+    Bullet,
+    Projectile,
+    Explosion,
+}
+
+/// Asks for damage to be dealt to an entity with Health
+#[derive(Clone, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub amount: f32,
+    pub kind: DamageKind,
+    pub point: Vec3,
+    /// Of the surface that was hit, pointing out of it
+    pub normal: Vec3,
+}
+
+/// Damage that was taken, the amount after armor. For hit reactions
+pub struct Damaged(pub DamageEvent);
+
+/// Health dropped to zero. Sent once per death
+pub struct Died {
+    pub entity: Entity,
+    pub source: Option<Entity>,
+    pub kind: DamageKind,
+}
+
+/// How an entity with Health takes damage, entities without it take all of it
+#[derive(Component, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct Armor {
+    /// Taken off every hit, before resistances
+    pub flat: f32,
+    /// Fractions of each kind of damage that are ignored
+    pub bullet: f32,
+    pub projectile: f32,
+    pub explosion: f32,
+    /// Seconds after taking damage that further damage is ignored
+    pub invulnerability: f32,
+    #[serde(skip)]
+    pub invulnerable_timer: f32,
+}
+
+impl Armor {
+    fn reduce(&self, amount: f32, kind: DamageKind) -> f32 {
+        let resistance = match kind {
+            DamageKind::Bullet => self.bullet,
+            DamageKind::Projectile => self.projectile,
+            DamageKind::Explosion => self.explosion,
+        };
+        (amount - self.flat).max(0.0) * (1.0 - resistance.clamp(0.0, 1.0))
+    }
+}
+
+fn tick_invulnerability(time: Res<Time>, mut armors: Query<&mut Armor>) {
+    for mut armor in &mut armors {
+        if armor.invulnerable_timer > 0.0 {
+            armor.invulnerable_timer -= time.delta_seconds();
+        }
+    }
+}
+
+fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut targets: Query<(&mut Health, Option<&mut Armor>)>,
+    mut damaged_events: EventWriter<Damaged>,
+    mut died_events: EventWriter<Died>,
+) {
+    for event in damage_events.iter() {
+        let (mut health, armor) = match targets.get_mut(event.target) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        if health.0 <= 0.0 {
+            continue;
+        }
+        let amount = match armor {
+            Some(mut armor) => {
+                if armor.invulnerable_timer > 0.0 {
+                    continue;
+                }
+                armor.invulnerable_timer = armor.invulnerability;
+                armor.reduce(event.amount, event.kind)
+            }
+            None => event.amount,
+        };
+        if amount <= 0.0 {
+            continue;
+        }
+        health.0 -= amount;
+        damaged_events.send(Damaged(DamageEvent {
+            amount,
+            ..event.clone()
+        }));
+        if health.0 <= 0.0 {
+            died_events.send(Died {
+                entity: event.target,
+                source: event.source,
+                kind: event.kind,
+            });
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use bevy::prelude::*;
+#[cfg(feature = "client")]
+use bevy_egui::{egui, EguiInput, EguiSet};
+use bevy_fps_controller::controller::{
+    fps_controller_input, fps_controller_render, FpsController, FpsControllerInput,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+pub struct GamepadPlugin;
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            gamepad_to_controller
+                .after(fps_controller_input)
+                .before(fps_controller_render),
+        );
+        #[cfg(feature = "client")]
+        app.add_system(
+            gamepad_to_egui
+                .in_base_set(CoreSet::PreUpdate)
+                .after(EguiSet::ProcessInput)
+                .before(EguiSet::BeginFrame),
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct StickSettings {
+    /// Stick deflection below this is ignored
+    pub deadzone: f32,
+    /// Response curve exponent, 1.0 is linear. Higher gives finer control near the center
+    pub curve: f32,
+    /// Radians per second at full deflection
+    pub look_speed: f32,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        StickSettings {
+            deadzone: 0.15,
+            curve: 2.0,
+            look_speed: 3.0,
+        }
+    }
+}
+
+impl StickSettings {
+    /// Radial deadzone, then the response curve over what's left of the range
+    pub fn response(&self, stick: Vec2) -> Vec2 {
+        let len = stick.length();
+        if len <= self.deadzone {
+            return Vec2::ZERO;
+        }
+        let t = ((len - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        stick / len * t.powf(self.curve)
+    }
+}
+
+fn stick(
+    axes: &Axis<GamepadAxis>,
+    gamepad: Gamepad,
+    x: GamepadAxisType,
+    y: GamepadAxisType,
+) -> Vec2 {
+    Vec2::new(
+        axes.get(GamepadAxis::new(gamepad, x)).unwrap_or_default(),
+        axes.get(GamepadAxis::new(gamepad, y)).unwrap_or_default(),
+    )
+}
+
+/// Sticks add to the movement and look the fps controller read from keyboard and mouse
+pub fn gamepad_to_controller(
+    settings: Res<Settings>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    time: Res<Time>,
+    mut query: Query<(&FpsController, &mut FpsControllerInput)>,
+) {
+    for gamepad in gamepads.iter() {
+        let move_stick = settings.stick.response(stick(
+            &axes,
+            gamepad,
+            GamepadAxisType::LeftStickX,
+            GamepadAxisType::LeftStickY,
+        ));
+        let mut look_stick = settings.stick.response(stick(
+            &axes,
+            gamepad,
+            GamepadAxisType::RightStickX,
+            GamepadAxisType::RightStickY,
+        ));
+        if settings.invert_y {
+            look_stick.y = -look_stick.y;
+        }
+        let look = look_stick * settings.stick.look_speed * time.delta_seconds();
+        for (controller, mut input) in &mut query {
+            if !controller.enable_input {
+                continue;
+            }
+            input.movement.x = (input.movement.x + move_stick.x).clamp(-1.0, 1.0);
+            input.movement.z = (input.movement.z + move_stick.y).clamp(-1.0, 1.0);
+            input.yaw -= look.x;
+            if input.yaw.abs() > PI {
+                input.yaw = input.yaw.rem_euclid(TAU);
+            }
+            input.pitch = (input.pitch + look.y).clamp(-FRAC_PI_2 + 0.001, FRAC_PI_2 - 0.001);
+        }
+    }
+}
+
+/// While the cursor is free (menus, clock) the d-pad moves focus between widgets and
+/// south presses the focused one. Left/right adjust sliders.
+#[cfg(feature = "client")]
+fn gamepad_to_egui(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    fps_controller: Query<&FpsController>,
+    mut egui_input: Query<&mut EguiInput>,
+) {
+    if fps_controller
+        .iter()
+        .any(|controller| controller.enable_input)
+    {
+        return;
+    }
+    let mut keys = Vec::new();
+    for gamepad in gamepads.iter() {
+        let just_pressed =
+            |button_type| buttons.just_pressed(GamepadButton::new(gamepad, button_type));
+        if just_pressed(GamepadButtonType::DPadDown) {
+            keys.push((egui::Key::Tab, egui::Modifiers::NONE));
+        }
+        if just_pressed(GamepadButtonType::DPadUp) {
+            keys.push((egui::Key::Tab, egui::Modifiers::SHIFT));
+        }
+        if just_pressed(GamepadButtonType::DPadLeft) {
+            keys.push((egui::Key::ArrowLeft, egui::Modifiers::NONE));
+        }
+        if just_pressed(GamepadButtonType::DPadRight) {
+            keys.push((egui::Key::ArrowRight, egui::Modifiers::NONE));
+        }
+        if just_pressed(GamepadButtonType::South) {
+            keys.push((egui::Key::Enter, egui::Modifiers::NONE));
+        }
+    }
+    for mut egui_input in &mut egui_input {
+        for (key, modifiers) in &keys {
+            for pressed in [true, false] {
+                egui_input.0.events.push(egui::Event::Key {
+                    key: *key,
+                    pressed,
+                    repeat: false,
+                    modifiers: *modifiers,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use bevy::{pbr::NotShadowCaster, prelude::*, utils::HashMap};
+use bevy_fps_controller::controller::LogicalPlayer;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    levels::GameLevel,
+    menu::NewGame,
+    pause::PauseState,
+    player::AwaitingSpawnPoint,
+    replay::not_replaying,
+    run_timer::{record_personal_best, RunTimer, SplitRecords},
+    save::ContinueGame,
+    settings::Settings,
+    storage, AppState, GameLoading,
+};
+
+const GHOST_KEY: &str = "ghost";
+/// Seconds between recorded positions
+const SAMPLE_INTERVAL: f32 = 0.1;
+/// From the logical player's origin to the middle of its collider
+const GHOST_OFFSET: Vec3 = Vec3::new(0.0, 0.55, 0.0);
+
+pub struct GhostPlugin;
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelClock>()
+            .init_resource::<CurrentGhost>()
+            .insert_resource(BestGhost::load())
+            .add_startup_system(spawn_ghost)
+            .add_systems(
+                (
+                    reset_current_ghost,
+                    advance_level_clock,
+                    record_ghost
+                        .after(advance_level_clock)
+                        .run_if(in_state(AppState::InGame))
+                        .run_if(in_state(PauseState::Running))
+                        .run_if(not_replaying),
+                    update_ghost.after(advance_level_clock),
+                )
+                    .distributive_run_if(in_state(GameLoading::Loaded)),
+            )
+            .add_system(
+                save_best_ghost
+                    .after(record_personal_best)
+                    .run_if(not_replaying)
+                    .in_schedule(OnEnter(AppState::Results)),
+            );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+struct GhostSample {
+    /// Seconds since the level was entered
+    time: f32,
+    position: Vec3,
+}
+
+/// Where the player was in each level of a run. Only the last attempt at a level is kept
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+struct GhostRun {
+    levels: HashMap<GameLevel, Vec<GhostSample>>,
+}
+
+impl GhostRun {
+    /// None once the run had left the level
+    fn position(&self, level: &GameLevel, time: f32) -> Option<Vec3> {
+        let samples = self.levels.get(level)?;
+        let next = samples.partition_point(|sample| sample.time <= time);
+        if next == 0 {
+            return samples.first().map(|sample| sample.position);
+        }
+        let (a, b) = (samples[next - 1], *samples.get(next)?);
+        let t = (time - a.time) / (b.time - a.time);
+        Some(a.position.lerp(b.position, t))
+    }
+}
+
+/// Seconds since the current level was entered or restarted, not counting pauses
+#[derive(Resource, Default)]
+pub struct LevelClock(pub f32);
+
+/// The run in progress
+#[derive(Resource, Default)]
+struct CurrentGhost(GhostRun);
+
+/// The personal best run, shown as a ghost
+#[derive(Resource, Default)]
+struct BestGhost(GhostRun);
+
+impl BestGhost {
+    fn load() -> BestGhost {
+        if let Some(contents) = storage::read(GHOST_KEY) {
+            match serde_json::from_str(&contents) {
+                Ok(ghost) => return BestGhost(ghost),
+                Err(e) => warn!("Ignoring unreadable ghost: {e}"),
+            }
+        }
+        BestGhost::default()
+    }
+
+    fn write(&self) {
+        match serde_json::to_string(&self.0) {
+            Ok(contents) => storage::write(GHOST_KEY, &contents),
+            Err(e) => warn!("Failed to serialize ghost: {e}"),
+        }
+    }
+}
+
+#[derive(Component)]
+struct Ghost;
+
+fn spawn_ghost(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Capsule {
+                radius: 0.2,
+                depth: 0.9,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(0.3, 0.8, 1.0, 0.3),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(NotShadowCaster)
+        .insert(Ghost);
+}
+
+fn reset_current_ghost(
+    mut new_games: EventReader<NewGame>,
+    mut continue_games: EventReader<ContinueGame>,
+    mut current_ghost: ResMut<CurrentGhost>,
+) {
+    if new_games.iter().count() + continue_games.iter().count() > 0 {
+        *current_ghost = CurrentGhost::default();
+    }
+}
+
+fn advance_level_clock(
+    mut level_clock: ResMut<LevelClock>,
+    level: Res<State<GameLevel>>,
+    pause_state: Res<State<PauseState>>,
+    time: Res<Time>,
+) {
+    // Restarting a level sets the same state again, which also marks it changed
+    if level.is_changed() {
+        level_clock.0 = 0.0;
+    } else if pause_state.0 == PauseState::Running {
+        level_clock.0 += time.delta_seconds();
+    }
+}
+
+fn record_ghost(
+    mut current_ghost: ResMut<CurrentGhost>,
+    level_clock: Res<LevelClock>,
+    level: Res<State<GameLevel>>,
+    player: Query<&Transform, (With<LogicalPlayer>, Without<AwaitingSpawnPoint>)>,
+) {
+    if level.is_changed() {
+        current_ghost.0.levels.insert(level.0.clone(), Vec::new());
+    }
+    if let Some(transform) = player.iter().next() {
+        let samples = current_ghost.0.levels.entry(level.0.clone()).or_default();
+        let due = samples
+            .last()
+            .map_or(true, |last| level_clock.0 - last.time >= SAMPLE_INTERVAL);
+        if due {
+            samples.push(GhostSample {
+                time: level_clock.0,
+                position: transform.translation,
+            });
+        }
+    }
+}
+
+fn update_ghost(
+    settings: Res<Settings>,
+    best_ghost: Res<BestGhost>,
+    level_clock: Res<LevelClock>,
+    level: Res<State<GameLevel>>,
+    app_state: Res<State<AppState>>,
+    mut ghost: Query<(&mut Transform, &mut Visibility), With<Ghost>>,
+) {
+    let position = if settings.show_ghost && app_state.0 == AppState::InGame {
+        best_ghost.0.position(&level.0, level_clock.0)
+    } else {
+        None
+    };
+    for (mut transform, mut visibility) in &mut ghost {
+        match position {
+            Some(position) => {
+                transform.translation = position + GHOST_OFFSET;
+                *visibility = Visibility::Visible;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// Runs after the personal best is updated, so a new one has the same total as this run
+fn save_best_ghost(
+    current_ghost: Res<CurrentGhost>,
+    mut best_ghost: ResMut<BestGhost>,
+    run_timer: Res<RunTimer>,
+    records: Res<SplitRecords>,
+    time: Res<Time>,
+) {
+    let total = run_timer.elapsed(time.elapsed_seconds());
+    if total.is_some() && records.personal_best.as_ref().map(|pb| pb.total) == total {
+        best_ghost.0 = current_ghost.0.clone();
+        best_ghost.write();
+    }
+}
@@ -0,0 +1,270 @@
+use bevy::{
+    input::InputSystem,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_fps_controller::controller::{
+    fps_controller_input, fps_controller_render, FpsController, FpsControllerInput,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{settings::Settings, ClientSet};
+
+pub struct ActionsPlugin;
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .init_resource::<Rebinding>()
+            .init_resource::<UiWantsPointer>()
+            .add_system(
+                update_action_state
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputSystem),
+            )
+            // Before the menu sets Rebinding, so the press that picked the action isn't bound
+            .add_system(capture_rebind.before(ClientSet::Ui))
+            .add_system(
+                actions_to_controller
+                    .after(fps_controller_input)
+                    .before(fps_controller_render),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Fire,
+    Interact,
+    Menu,
+    Jump,
+    Crouch,
+    Run,
+    NextWeapon,
+    Reload,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::Fire,
+        Action::Interact,
+        Action::Menu,
+        Action::Jump,
+        Action::Crouch,
+        Action::Run,
+        Action::NextWeapon,
+        Action::Reload,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Fire => "FIRE",
+            Action::Interact => "INTERACT",
+            Action::Menu => "MENU",
+            Action::Jump => "JUMP",
+            Action::Crouch => "CROUCH",
+            Action::Run => "RUN",
+            Action::NextWeapon => "NEXT WEAPON",
+            Action::Reload => "RELOAD",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl InputBinding {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, InputBinding::Gamepad(_))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            InputBinding::Key(key) => format!("{:?}", key).to_uppercase(),
+            InputBinding::Mouse(button) => format!("MOUSE {:?}", button).to_uppercase(),
+            InputBinding::Gamepad(button) => format!("PAD {:?}", button).to_uppercase(),
+        }
+    }
+}
+
+/// Every input that triggers each action. Each action has at most one keyboard/mouse
+/// binding and one gamepad binding
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct InputBindings(pub HashMap<Action, Vec<InputBinding>>);
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use GamepadButtonType as Pad;
+        use InputBinding::*;
+        InputBindings(
+            [
+                (
+                    Action::Fire,
+                    vec![Mouse(MouseButton::Left), Gamepad(Pad::RightTrigger2)],
+                ),
+                (Action::Interact, vec![Key(KeyCode::E), Gamepad(Pad::West)]),
+                (Action::Menu, vec![Key(KeyCode::Tab), Gamepad(Pad::Start)]),
+                (Action::Jump, vec![Key(KeyCode::Space), Gamepad(Pad::South)]),
+                (
+                    Action::Crouch,
+                    vec![Key(KeyCode::LControl), Gamepad(Pad::East)],
+                ),
+                (
+                    Action::Run,
+                    vec![Key(KeyCode::LShift), Gamepad(Pad::LeftThumb)],
+                ),
+                (
+                    Action::NextWeapon,
+                    vec![Key(KeyCode::Q), Gamepad(Pad::North)],
+                ),
+                (
+                    Action::Reload,
+                    vec![Key(KeyCode::R), Gamepad(Pad::RightThumb)],
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: Action) -> &[InputBinding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn label(&self, action: Action) -> String {
+        self.get(action)
+            .iter()
+            .map(|binding| binding.label())
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+
+    /// Replaces the existing binding of the same kind (keyboard/mouse or gamepad)
+    pub fn rebind(&mut self, action: Action, binding: InputBinding) {
+        let bindings = self.0.entry(action).or_default();
+        bindings.retain(|b| b.is_gamepad() != binding.is_gamepad());
+        bindings.push(binding);
+    }
+}
+
+/// Which actions are held this frame, from all bound inputs
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+/// The action waiting for an input to bind to, set from the rebinding menu
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<Action>);
+
+/// The pointer is over the menu, so clicks are for the menu rather than the gun
+#[derive(Resource, Default)]
+pub struct UiWantsPointer(pub bool);
+
+pub fn update_action_state(
+    mut action_state: ResMut<ActionState>,
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+) {
+    action_state.pressed.clear();
+    action_state.just_pressed.clear();
+    // Don't fire actions with the input that's being bound
+    if rebinding.0.is_some() {
+        return;
+    }
+    for action in Action::ALL {
+        for binding in settings.bindings.get(action) {
+            let (pressed, just_pressed) = match binding {
+                InputBinding::Key(key) => (keys.pressed(*key), keys.just_pressed(*key)),
+                InputBinding::Mouse(button) => (
+                    mouse_buttons.pressed(*button),
+                    mouse_buttons.just_pressed(*button),
+                ),
+                InputBinding::Gamepad(button_type) => (
+                    gamepads.iter().any(|gamepad| {
+                        gamepad_buttons.pressed(GamepadButton::new(gamepad, *button_type))
+                    }),
+                    gamepads.iter().any(|gamepad| {
+                        gamepad_buttons.just_pressed(GamepadButton::new(gamepad, *button_type))
+                    }),
+                ),
+            };
+            if pressed {
+                action_state.pressed.insert(action);
+            }
+            if just_pressed {
+                action_state.just_pressed.insert(action);
+            }
+        }
+    }
+}
+
+fn capture_rebind(
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    if let Some(action) = rebinding.0 {
+        if keys.just_pressed(KeyCode::Escape) {
+            rebinding.0 = None;
+            return;
+        }
+        let binding = keys
+            .get_just_pressed()
+            .next()
+            .map(|key| InputBinding::Key(*key))
+            .or_else(|| {
+                mouse_buttons
+                    .get_just_pressed()
+                    .next()
+                    .map(|button| InputBinding::Mouse(*button))
+            })
+            .or_else(|| {
+                gamepad_buttons
+                    .get_just_pressed()
+                    .next()
+                    .map(|button| InputBinding::Gamepad(button.button_type))
+            });
+        if let Some(binding) = binding {
+            settings.bindings.rebind(action, binding);
+            rebinding.0 = None;
+        }
+    }
+}
+
+/// Drive the fps controller's jump, crouch and run from actions instead of its own key checks
+pub fn actions_to_controller(
+    action_state: Res<ActionState>,
+    mut query: Query<(&FpsController, &mut FpsControllerInput)>,
+) {
+    for (controller, mut input) in &mut query {
+        input.fly = false;
+        if controller.enable_input {
+            input.jump = action_state.pressed(Action::Jump);
+            input.crouch = action_state.pressed(Action::Crouch);
+            input.sprint = action_state.pressed(Action::Run);
+        }
+    }
+}
@@ -1,32 +1,35 @@
-use bevy::{math::vec3, prelude::*};
+use bevy::prelude::*;
+use bevy_fps_controller::controller::FpsController;
+use serde::{Deserialize, Serialize};
 
-use crate::GameLoading;
+use crate::{
+    physics::AddTrimeshPhysics,
+    pickups::PickupSpawns,
+    triggers::{trigger_sensor, SpawnTriggers, TriggerKind},
+    units::EnemySpawns,
+    GameLoading,
+};
 
-use self::{
-    bathroom::{despawn_bathroom, spawn_bathroom},
-    bf1::{despawn_bf1, spawn_bf1},
-    bf_start::{despawn_bfstart, spawn_bfstart},
-    bfa::{despawn_bfa1, despawn_bfa2, despawn_bfa3, spawn_bfa1, spawn_bfa2, spawn_bfa3},
-    controlroom::{despawn_control_room, spawn_control_room},
-    copier::{despawn_copier, spawn_copier},
-    houses::{despawn_houses, spawn_houses},
-    kitchen::{despawn_kitchen, spawn_kitchen},
-    shower::{despawn_shower, spawn_shower},
-    urban::{despawn_urban, spawn_urban},
+#[cfg(feature = "client")]
+use crate::{
+    materials::{
+        light_shafts::{LightShaftsMaterial, SetLightShaftMaterial},
+        pbr_material::{EnvSettings, SetGrassMaterial, SetGrassMaterial2},
+        plant_material::{PlantsMaterial, SetPlantsMaterial},
+        skybox::SkyBoxMaterial,
+    },
+    run_timer::RunTimer,
+    ui::{HasEnteredControlRoom, TextFeed},
+    AppState,
 };
 
-pub mod bathroom;
-pub mod bf1;
-pub mod bf_start;
-pub mod bfa;
-pub mod controlroom;
-pub mod copier;
-pub mod houses;
-pub mod kitchen;
-pub mod shower;
-pub mod urban;
+use self::manifest::Levels;
+#[cfg(feature = "client")]
+use self::manifest::{EnvProperties, LevelScene, SceneMaterial};
+
+pub mod manifest;
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States, Serialize, Deserialize)]
 pub enum GameLevel {
     #[default]
     Kitchen,
@@ -43,197 +46,180 @@ pub enum GameLevel {
     ControlRoom,
 }
 
-impl GameLevel {
-    pub fn spawn_pos(&self) -> Vec3 {
-        //TODO don't hardcode
-        match self {
-            GameLevel::Houses => vec3(0.0, 0.1, 0.0),
-            GameLevel::Kitchen => vec3(0.0, 0.1, 0.0),
-            GameLevel::Urban => vec3(0.0, 0.1, 0.0),
-            GameLevel::Shower => vec3(0.0, 0.1, 0.0),
-            GameLevel::Copier => vec3(0.0, 0.1, 0.0),
-            GameLevel::BFStart => vec3(0.0, 0.1, 0.0),
-            GameLevel::Bathroom => vec3(0.0, 0.1, 0.0),
-            GameLevel::BF1 => vec3(0.0, 0.1, 0.0),
-            GameLevel::BFA1 => vec3(-271.0, 0.2, 44.0),
-            GameLevel::BFA2 => vec3(-1.35, 0.2, 23.0),
-            GameLevel::BFA3 => vec3(372.0, 0.2, -9.5),
-            GameLevel::ControlRoom => vec3(0.0, 2.2, -15.8),
+/// Spawns a level's scenes, colliders, spawn points and triggers when it's entered. The client
+/// also gives it a skybox, materials and intro text
+pub struct LevelsPlugin;
+impl Plugin for LevelsPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "client")]
+        app.add_systems((
+            start_game_timer
+                .in_schedule(OnEnter(GameLevel::Kitchen))
+                .run_if(in_state(GameLoading::Loaded)),
+            enter_control_room
+                .in_schedule(OnEnter(GameLevel::ControlRoom))
+                .run_if(in_state(GameLoading::Loaded)),
+        ));
+        for level in GameLevel::variants() {
+            #[cfg(feature = "client")]
+            app.add_system(
+                spawn_skybox_and_intro
+                    .in_schedule(OnEnter(level.clone()))
+                    .run_if(in_state(GameLoading::Loaded)),
+            );
+            app.add_systems((
+                spawn_level
+                    .in_schedule(OnEnter(level.clone()))
+                    .run_if(in_state(GameLoading::Loaded)),
+                despawn_level.in_schedule(OnExit(level)),
+            ));
         }
     }
-    pub fn teleporter_pos_close_enough(&self, pos: Vec3) -> bool {
-        //TODO don't hardcode
-        match self {
-            GameLevel::Houses => false,
-            GameLevel::Kitchen => false,
-            GameLevel::Urban => false,
-            GameLevel::Shower => false,
-            GameLevel::Copier => false,
-            GameLevel::Bathroom => false,
-            GameLevel::BFStart => vec3(-106.31, -35.4, -44.725).distance(pos) < 15.0,
-            GameLevel::BF1 => vec3(58.6389, -453.065, -640.837).distance(pos) < 8.0,
-            GameLevel::BFA1 => vec3(-350.534, 0.0, -79.1253).distance(pos) < 8.0,
-            GameLevel::BFA2 => vec3(-1.53535, 0.0, -195.286).distance(pos) < 8.0,
-            GameLevel::BFA3 => vec3(372.869, 0.0, -91.5084).distance(pos) < 8.0,
-            GameLevel::ControlRoom => vec3(0.0, -9.0, 18.0).distance(pos) < 4.0,
+}
+
+/// Marks every entity that belongs to a level so it can be cleaned up when the level is exited
+#[derive(Component)]
+pub struct LevelEntity(pub GameLevel);
+
+pub fn despawn_level(mut commands: Commands, query: Query<Entity, With<LevelEntity>>) {
+    for entity in &query {
+        if commands.get_entity(entity).is_some() {
+            commands.entity(entity).despawn_recursive();
         }
     }
-    pub fn clock_position_close_enough(&self, pos: Vec3) -> bool {
-        //TODO don't hardcode
-        match self {
-            GameLevel::Houses => vec3(-50.3636, 0.0, -12.6446).distance(pos) < 1.5,
-            GameLevel::Kitchen => vec3(-0.139801, 0.0, 2.02768).distance(pos) < 1.5,
-            GameLevel::Urban => vec3(-4.65948, 0.0, -7.78706).distance(pos) < 1.5,
-            GameLevel::Shower => vec3(1.00083, 0.0, -0.709787).distance(pos) < 0.8,
-            GameLevel::Copier => vec3(-0.213056, 0.0, -0.637783).distance(pos) < 0.8,
-            GameLevel::Bathroom => vec3(0.3016, 0.0, 0.410778).distance(pos) < 1.5,
-            GameLevel::BFStart => false,
-            GameLevel::BF1 => false,
-            GameLevel::BFA1 => false,
-            GameLevel::BFA2 => false,
-            GameLevel::BFA3 => false,
-            GameLevel::ControlRoom => false,
-        }
+}
+
+pub fn spawn_level(
+    mut commands: Commands,
+    level: Res<State<GameLevel>>,
+    levels: Levels,
+    asset_server: Res<AssetServer>,
+    mut fps_controllers: Query<&mut FpsController>,
+) {
+    let props = levels.get(&level.0);
+    for mut fps_controller in &mut fps_controllers {
+        fps_controller.gravity = props.gravity;
     }
-    pub fn teleporter_dest(&self) -> GameLevel {
-        //TODO don't hardcode
-        match self {
-            GameLevel::Kitchen => GameLevel::BFStart,
-            GameLevel::BFStart => GameLevel::Shower,
-            GameLevel::Shower => GameLevel::BFA1,
-            GameLevel::BFA1 => GameLevel::Copier,
-            GameLevel::Copier => GameLevel::BFA2,
-            GameLevel::BFA2 => GameLevel::Bathroom,
-            GameLevel::Bathroom => GameLevel::BFA3,
-            GameLevel::BFA3 => GameLevel::Houses,
-            GameLevel::Houses => GameLevel::BF1,
-            GameLevel::BF1 => GameLevel::Urban,
-            GameLevel::Urban => GameLevel::ControlRoom,
-            GameLevel::ControlRoom => GameLevel::Kitchen,
+    for scene in &props.scenes {
+        let mut entity = commands.spawn(SceneBundle {
+            scene: asset_server.load(&scene.scene),
+            ..default()
+        });
+        entity.insert(LevelEntity(level.0.clone()));
+        if scene.physics {
+            entity.insert(AddTrimeshPhysics);
         }
-    }
-    pub fn teleporter_code(code: &str) -> Option<GameLevel> {
-        //TODO don't hardcode
-        match code {
-            "0625" => Some(GameLevel::Kitchen),
-            "1332" => Some(GameLevel::BFStart),
-            "0719" => Some(GameLevel::Shower),
-            "1512" => Some(GameLevel::BFA1),
-            "1514" => Some(GameLevel::Copier),
-            "0655" => Some(GameLevel::BFA2),
-            "1207" => Some(GameLevel::Bathroom),
-            "0201" => Some(GameLevel::BFA3),
-            "2142" => Some(GameLevel::Houses),
-            "0722" => Some(GameLevel::BF1),
-            "2306" => Some(GameLevel::Urban),
-            "0121" => Some(GameLevel::ControlRoom),
-            _ => None,
+        #[cfg(feature = "client")]
+        add_scene_materials(&mut entity, scene, &props.env_settings);
+        if scene.enemy_spawns {
+            entity.insert(EnemySpawns);
         }
-    }
-    pub fn player_can_jump(&self) -> bool {
-        match self {
-            GameLevel::Kitchen => false,
-            GameLevel::BFStart => true,
-            GameLevel::Shower => false,
-            GameLevel::BFA1 => true,
-            GameLevel::Copier => false,
-            GameLevel::BFA2 => true,
-            GameLevel::Bathroom => false,
-            GameLevel::BFA3 => true,
-            GameLevel::Houses => false,
-            GameLevel::BF1 => true,
-            GameLevel::Urban => false,
-            GameLevel::ControlRoom => true,
+        if scene.triggers {
+            entity.insert(SpawnTriggers);
+        }
+        if scene.pickups {
+            entity.insert(PickupSpawns);
         }
     }
-    pub fn show_gun(&self) -> bool {
-        match self {
-            GameLevel::Kitchen => false,
-            GameLevel::BFStart => true,
-            GameLevel::Shower => false,
-            GameLevel::BFA1 => true,
-            GameLevel::Copier => false,
-            GameLevel::BFA2 => true,
-            GameLevel::Bathroom => false,
-            GameLevel::BFA3 => true,
-            GameLevel::Houses => false,
-            GameLevel::BF1 => true,
-            GameLevel::Urban => false,
-            GameLevel::ControlRoom => true,
+    for (kind, zone) in [
+        (TriggerKind::Teleporter, &props.teleporter),
+        (TriggerKind::Clock, &props.clock),
+    ] {
+        if let Some(zone) = zone {
+            commands
+                .spawn(trigger_sensor(kind, zone.radius))
+                .insert(TransformBundle::from_transform(
+                    Transform::from_translation(zone.pos),
+                ))
+                .insert(LevelEntity(level.0.clone()));
         }
     }
-    pub fn show_drones_dead_msg(&self) -> bool {
-        match self {
-            GameLevel::Kitchen => false,
-            GameLevel::BFStart => false,
-            GameLevel::Shower => false,
-            GameLevel::BFA1 => true,
-            GameLevel::Copier => false,
-            GameLevel::BFA2 => true,
-            GameLevel::Bathroom => false,
-            GameLevel::BFA3 => true,
-            GameLevel::Houses => false,
-            GameLevel::BF1 => true,
-            GameLevel::Urban => false,
-            GameLevel::ControlRoom => true,
+}
+
+#[cfg(feature = "client")]
+fn add_scene_materials(
+    entity: &mut bevy::ecs::system::EntityCommands,
+    scene: &LevelScene,
+    env: &EnvProperties,
+) {
+    if scene.env {
+        entity.insert(EnvSettings {
+            env_spec: env.env_spec,
+            env_diff: env.env_diff,
+            emit_mult: env.emit_mult,
+        });
+    }
+    if let Some(light_shafts) = scene.light_shafts {
+        entity.insert(SetLightShaftMaterial(LightShaftsMaterial {
+            color: light_shafts.color,
+            shaft: light_shafts.shaft,
+            dust: light_shafts.dust,
+            dust_size: light_shafts.dust_size,
+            dust_qty_sub: light_shafts.dust_qty_sub,
+            dust_speed: light_shafts.dust_speed,
+        }));
+    }
+    match scene.material {
+        Some(SceneMaterial::Grass) => {
+            entity.insert(SetGrassMaterial);
+        }
+        Some(SceneMaterial::Grass2) => {
+            entity.insert(SetGrassMaterial2);
+        }
+        Some(SceneMaterial::Plants) => {
+            entity.insert(SetPlantsMaterial(PlantsMaterial {}));
         }
+        None => (),
     }
 }
 
-pub struct LevelsPlugin;
-impl Plugin for LevelsPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_systems((
-            spawn_bathroom
-                .in_schedule(OnEnter(GameLevel::Bathroom))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_houses
-                .in_schedule(OnEnter(GameLevel::Houses))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_kitchen
-                .in_schedule(OnEnter(GameLevel::Kitchen))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_urban
-                .in_schedule(OnEnter(GameLevel::Urban))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_shower
-                .in_schedule(OnEnter(GameLevel::Shower))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_copier
-                .in_schedule(OnEnter(GameLevel::Copier))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_bfstart
-                .in_schedule(OnEnter(GameLevel::BFStart))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_bf1
-                .in_schedule(OnEnter(GameLevel::BF1))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_bfa1
-                .in_schedule(OnEnter(GameLevel::BFA1))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_bfa2
-                .in_schedule(OnEnter(GameLevel::BFA2))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_bfa3
-                .in_schedule(OnEnter(GameLevel::BFA3))
-                .run_if(in_state(GameLoading::Loaded)),
-            spawn_control_room
-                .in_schedule(OnEnter(GameLevel::ControlRoom))
-                .run_if(in_state(GameLoading::Loaded)),
-        ))
-        .add_systems((
-            despawn_bathroom.in_schedule(OnExit(GameLevel::Bathroom)),
-            despawn_houses.in_schedule(OnExit(GameLevel::Houses)),
-            despawn_kitchen.in_schedule(OnExit(GameLevel::Kitchen)),
-            despawn_urban.in_schedule(OnExit(GameLevel::Urban)),
-            despawn_shower.in_schedule(OnExit(GameLevel::Shower)),
-            despawn_copier.in_schedule(OnExit(GameLevel::Copier)),
-            despawn_bfstart.in_schedule(OnExit(GameLevel::BFStart)),
-            despawn_bf1.in_schedule(OnExit(GameLevel::BF1)),
-            despawn_bfa1.in_schedule(OnExit(GameLevel::BFA1)),
-            despawn_bfa2.in_schedule(OnExit(GameLevel::BFA2)),
-            despawn_bfa3.in_schedule(OnExit(GameLevel::BFA3)),
-            despawn_control_room.in_schedule(OnExit(GameLevel::ControlRoom)),
-        ));
+#[cfg(feature = "client")]
+fn spawn_skybox_and_intro(
+    mut commands: Commands,
+    level: Res<State<GameLevel>>,
+    levels: Levels,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<SkyBoxMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut text_feed: ResMut<TextFeed>,
+) {
+    let props = levels.get(&level.0);
+    if let Some(intro_text) = &props.intro_text {
+        text_feed.push(intro_text);
+    }
+    if let Some(skybox) = &props.skybox {
+        commands
+            .spawn(MaterialMeshBundle {
+                mesh: meshes.add(Mesh::from(shape::Cube { size: skybox.size })),
+                material: materials.add(SkyBoxMaterial {
+                    env_texture: Some(asset_server.load(&skybox.texture)),
+                    uv_offset: skybox.uv_offset,
+                    brightness: skybox.brightness,
+                    contrast: skybox.contrast,
+                }),
+                ..default()
+            })
+            .insert(LevelEntity(level.0.clone()));
     }
 }
+
+#[cfg(feature = "client")]
+fn start_game_timer(
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut run_timer: ResMut<RunTimer>,
+    time: Res<Time>,
+    has_entered_control_room: Res<HasEnteredControlRoom>,
+) {
+    if !run_timer.is_started() {
+        run_timer.start(time.elapsed_seconds());
+    }
+    if has_entered_control_room.0 {
+        run_timer.finish(time.elapsed_seconds());
+        next_app_state.set(AppState::Results);
+    }
+}
+
+#[cfg(feature = "client")]
+fn enter_control_room(mut has_entered_control_room: ResMut<HasEnteredControlRoom>) {
+    has_entered_control_room.0 = true;
+}
@@ -0,0 +1,176 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::assets::LevelAssets;
+
+use super::GameLevel;
+
+/// A sphere around a point of interest in a level (teleporter, clock, etc...)
+/// Spawned as a trigger for levels that don't have trigger nodes in their gltf
+#[derive(Deserialize, Clone, Debug)]
+pub struct LevelZone {
+    pub pos: Vec3,
+    pub radius: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SkyBoxProperties {
+    pub texture: String,
+    pub size: f32,
+    pub uv_offset: Vec2,
+    pub brightness: f32,
+    pub contrast: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub struct EnvProperties {
+    pub env_spec: f32,
+    pub env_diff: f32,
+    pub emit_mult: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct LightShaftProperties {
+    pub color: Vec3,
+    pub shaft: f32,
+    pub dust: f32,
+    pub dust_size: f32,
+    pub dust_qty_sub: f32,
+    pub dust_speed: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum SceneMaterial {
+    Grass,
+    Grass2,
+    Plants,
+}
+
+/// One gltf scene that makes up part of a level
+#[derive(Deserialize, Clone, Debug)]
+pub struct LevelScene {
+    pub scene: String,
+    /// Generate trimesh colliders for every mesh in the scene
+    #[serde(default)]
+    pub physics: bool,
+    /// Apply the level's env_settings to the scene
+    #[serde(default)]
+    pub env: bool,
+    #[serde(default)]
+    pub light_shafts: Option<LightShaftProperties>,
+    #[serde(default)]
+    pub material: Option<SceneMaterial>,
+    /// Scan the scene for enemyspawn nodes
+    #[serde(default)]
+    pub enemy_spawns: bool,
+    /// Scan the scene for trigger nodes
+    #[serde(default)]
+    pub triggers: bool,
+    /// Scan the scene for pickup nodes
+    #[serde(default)]
+    pub pickups: bool,
+}
+
+fn default_gravity() -> f32 {
+    crate::character_controller::GRAVITY
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LevelProperties {
+    pub level: GameLevel,
+    pub spawn_pos: Vec3,
+    #[serde(default)]
+    pub teleporter: Option<LevelZone>,
+    #[serde(default)]
+    pub clock: Option<LevelZone>,
+    pub teleporter_dest: GameLevel,
+    pub teleporter_code: String,
+    #[serde(default)]
+    pub player_can_jump: bool,
+    #[serde(default)]
+    pub show_gun: bool,
+    #[serde(default)]
+    pub show_drones_dead_msg: bool,
+    /// Pushed to the text feed when the level is entered
+    #[serde(default)]
+    pub intro_text: Option<String>,
+    #[serde(default = "default_gravity")]
+    pub gravity: f32,
+    #[serde(default)]
+    pub skybox: Option<SkyBoxProperties>,
+    #[serde(default)]
+    pub env_settings: EnvProperties,
+    #[serde(default)]
+    pub scenes: Vec<LevelScene>,
+}
+
+#[derive(Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "6e3c1c1a-2a6b-4f43-9d43-5b0c3a8f7e21"]
+pub struct LevelManifest {
+    pub levels: Vec<LevelProperties>,
+}
+
+impl LevelManifest {
+    pub fn get(&self, level: &GameLevel) -> Option<&LevelProperties> {
+        self.levels.iter().find(|props| &props.level == level)
+    }
+
+    pub fn teleporter_code(&self, code: &str) -> Option<GameLevel> {
+        self.levels
+            .iter()
+            .find(|props| props.teleporter_code == code)
+            .map(|props| props.level.clone())
+    }
+}
+
+#[derive(Default)]
+pub struct LevelManifestLoader;
+
+impl AssetLoader for LevelManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest = serde_json::from_slice::<LevelManifest>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["levels.json"]
+    }
+}
+
+/// Looks up the per level properties from the loaded level manifest
+#[derive(SystemParam)]
+pub struct Levels<'w> {
+    level_assets: Res<'w, LevelAssets>,
+    manifests: Res<'w, Assets<LevelManifest>>,
+}
+
+impl<'w> Levels<'w> {
+    pub fn manifest(&self) -> &LevelManifest {
+        self.manifests
+            .get(&self.level_assets.manifest)
+            .expect("level manifest should be loaded")
+    }
+
+    pub fn get(&self, level: &GameLevel) -> &LevelProperties {
+        self.manifest()
+            .get(level)
+            .unwrap_or_else(|| panic!("{:?} is missing from the level manifest", level))
+    }
+
+    pub fn teleporter_code(&self, code: &str) -> Option<GameLevel> {
+        self.manifest().teleporter_code(code)
+    }
+}
@@ -0,0 +1,131 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+pub mod assets;
+#[cfg(feature = "client")]
+pub mod audio;
+pub mod character_controller;
+pub mod damage;
+pub mod gamepad;
+#[cfg(feature = "client")]
+pub mod ghost;
+pub mod input;
+pub mod levels;
+pub mod materials;
+#[cfg(feature = "client")]
+pub mod menu;
+pub mod navmesh;
+pub mod pause;
+pub mod physics;
+pub mod pickups;
+pub mod player;
+#[cfg(feature = "client")]
+pub mod replay;
+#[cfg(feature = "client")]
+pub mod run_timer;
+#[cfg(feature = "client")]
+pub mod save;
+pub mod settings;
+pub mod sfx;
+pub mod storage;
+pub mod triggers;
+#[cfg(feature = "client")]
+pub mod ui;
+pub mod units;
+pub mod util;
+pub mod weapons;
+
+use bevy::prelude::*;
+use bevy_fps_controller::controller::FpsControllerPlugin;
+use damage::DamagePlugin;
+use input::ActionsPlugin;
+use levels::{
+    manifest::{LevelManifest, LevelManifestLoader},
+    GameLevel, LevelsPlugin,
+};
+use materials::pbr_material;
+use navmesh::NavMeshPlugin;
+use pause::PausePlugin;
+use physics::PhysicsStuff;
+use pickups::PickupsPlugin;
+use player::PlayerPlugin;
+use rand_pcg::Pcg32;
+use sfx::PlaySfx;
+use triggers::TriggersPlugin;
+use units::{
+    kinds::{UnitKindManifest, UnitKindManifestLoader},
+    UnitsPlugin,
+};
+use weapons::{WeaponManifest, WeaponManifestLoader};
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum GameLoading {
+    #[default]
+    AssetLoading,
+    Loaded,
+}
+
+/// Which screen is up once loading is done
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    InGame,
+    Results,
+}
+
+/// Seconds per step of CoreSchedule::FixedUpdate, where units run
+pub const TIMESTEP: f32 = 1.0 / 60.0;
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(pub Pcg32);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(Pcg32::new(0xcafef00dd15ea5e5, 0xa02bdbf7bb3c0a7))
+    }
+}
+
+#[derive(Component)]
+pub struct Health(pub f32);
+
+/// Client systems that gameplay systems are ordered around. Nothing is in them without the
+/// client feature
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ClientSet {
+    /// The egui menus, which can teleport the player or take the pointer
+    Ui,
+    /// Grabbing and releasing the cursor, which pauses and unpauses
+    Cursor,
+}
+
+/// Physics, levels, the player, units and triggers. Nothing here needs a window, a GPU or audio, so
+/// it also runs under MinimalPlugins for tests. Settings and the asset collections are
+/// inserted by the client, or by the test harness
+pub struct GameplayPlugin;
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameRng::default())
+            .insert_resource(FixedTime::new_from_secs(TIMESTEP))
+            .add_state::<GameLoading>()
+            .add_state::<GameLevel>()
+            .add_state::<AppState>()
+            .add_event::<PlaySfx>()
+            .add_asset::<LevelManifest>()
+            .init_asset_loader::<LevelManifestLoader>()
+            .add_asset::<UnitKindManifest>()
+            .init_asset_loader::<UnitKindManifestLoader>()
+            .add_asset::<WeaponManifest>()
+            .init_asset_loader::<WeaponManifestLoader>()
+            .add_plugin(PhysicsStuff)
+            .add_plugin(DamagePlugin)
+            .add_plugin(NavMeshPlugin)
+            .add_plugin(FpsControllerPlugin)
+            .add_plugin(ActionsPlugin)
+            .add_plugin(TriggersPlugin)
+            .add_plugin(UnitsPlugin)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(PickupsPlugin)
+            .add_plugin(LevelsPlugin)
+            .add_plugin(PausePlugin);
+    }
+}
@@ -0,0 +1,200 @@
+use bevy::{app::AppExit, prelude::*, window::CursorGrabMode};
+use bevy_egui::{egui, EguiContexts};
+use bevy_fps_controller::controller::FpsController;
+
+use crate::{
+    input::Rebinding,
+    levels::GameLevel,
+    player::{RunStats, TeleportPlayer},
+    replay::{PlayReplay, Replay},
+    run_timer::{RunTimer, SplitRecords},
+    save::{ContinueGame, DiscoveredCodes, SavedGame},
+    settings::{DifficultyOverride, Settings},
+    ui::{get_visuals, set_text_styles, settings_ui, HasEnteredControlRoom, TextFeed},
+    AppState, GameLoading, GameRng,
+};
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NewGame>()
+            .add_systems(
+                (
+                    main_menu.run_if(in_state(AppState::MainMenu)),
+                    results_ui.run_if(in_state(AppState::Results)),
+                    new_game,
+                )
+                    .distributive_run_if(in_state(GameLoading::Loaded)),
+            )
+            .add_system(free_cursor.in_schedule(OnEnter(AppState::Results)));
+    }
+}
+
+/// Start a fresh run from the kitchen
+pub struct NewGame;
+
+pub fn new_game(
+    mut events: EventReader<NewGame>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut teleports: EventWriter<TeleportPlayer>,
+    mut run_timer: ResMut<RunTimer>,
+    mut has_entered_control_room: ResMut<HasEnteredControlRoom>,
+    mut discovered_codes: ResMut<DiscoveredCodes>,
+    mut run_stats: ResMut<RunStats>,
+    mut text_feed: ResMut<TextFeed>,
+    mut rng: ResMut<GameRng>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+    // Same seed every run so replays play out the same way
+    *rng = GameRng::default();
+    // The timer starts when the kitchen is entered
+    *run_timer = RunTimer::default();
+    has_entered_control_room.0 = false;
+    *discovered_codes = DiscoveredCodes::default();
+    *run_stats = RunStats::default();
+    *text_feed = TextFeed::default();
+    next_app_state.set(AppState::InGame);
+    teleports.send(TeleportPlayer(GameLevel::Kitchen));
+}
+
+fn free_cursor(mut windows: Query<&mut Window>, mut fps_controller: Query<&mut FpsController>) {
+    for mut window in &mut windows {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+    for mut fps_controller in &mut fps_controller {
+        fps_controller.enable_input = false;
+    }
+}
+
+fn main_menu(
+    mut contexts: EguiContexts,
+    saved: Res<SavedGame>,
+    mut new_game: EventWriter<NewGame>,
+    mut continue_game: EventWriter<ContinueGame>,
+    replay: Res<Replay>,
+    mut play_replay: EventWriter<PlayReplay>,
+    mut settings: ResMut<Settings>,
+    mut difficulty_override: ResMut<DifficultyOverride>,
+    mut rebinding: ResMut<Rebinding>,
+    mut show_settings: Local<bool>,
+    #[allow(unused_mut, unused_variables)] mut exit: EventWriter<AppExit>,
+) {
+    let ctx = contexts.ctx_mut();
+    ctx.set_visuals(get_visuals());
+    set_text_styles(ctx);
+    egui::Window::new("main menu")
+        .title_bar(false)
+        .collapsible(false)
+        .movable(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.vertical_centered_justified(|ui| {
+                if *show_settings {
+                    settings_ui(ui, &mut settings, &mut difficulty_override, &mut rebinding);
+                    if ui.button("BACK").clicked() {
+                        *show_settings = false;
+                    }
+                    return;
+                }
+                ui.heading("TRAVERSE");
+                if ui.button("NEW GAME").clicked() {
+                    new_game.send(NewGame);
+                }
+                if let Some(save) = &saved.0 {
+                    if ui
+                        .button(format!("CONTINUE ({:?} {:.1})", save.level, save.elapsed))
+                        .clicked()
+                    {
+                        continue_game.send(ContinueGame);
+                    }
+                }
+                if replay.has_recording() && ui.button("WATCH LAST RUN").clicked() {
+                    play_replay.send(PlayReplay);
+                }
+                if ui.button("SETTINGS").clicked() {
+                    *show_settings = true;
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("QUIT").clicked() {
+                    exit.send(AppExit);
+                }
+            })
+        });
+}
+
+fn results_ui(
+    mut contexts: EguiContexts,
+    run_stats: Res<RunStats>,
+    run_timer: Res<RunTimer>,
+    records: Res<SplitRecords>,
+    time: Res<Time>,
+    mut new_game: EventWriter<NewGame>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    #[allow(unused_mut, unused_variables)] mut exported_to: Local<Option<String>>,
+) {
+    let total = run_timer
+        .elapsed(time.elapsed_seconds())
+        .unwrap_or_default();
+    let ctx = contexts.ctx_mut();
+    ctx.set_visuals(get_visuals());
+    set_text_styles(ctx);
+    egui::Window::new("results")
+        .title_bar(false)
+        .collapsible(false)
+        .movable(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.vertical_centered_justified(|ui| {
+                ui.heading("YOU DID IT! NICE WORK.");
+                ui.label(format!("TOTAL TIME {:.1}", total));
+                if let Some(pb) = &records.personal_best {
+                    if pb.total == total {
+                        ui.colored_label(egui::Color32::GOLD, "NEW PERSONAL BEST");
+                    } else {
+                        ui.label(format!("PERSONAL BEST {:.1}", pb.total));
+                    }
+                }
+                egui::Grid::new("splits").striped(true).show(ui, |ui| {
+                    for (level, level_time) in run_timer.segments(total) {
+                        ui.label(format!("{:?}", level).to_uppercase());
+                        let gold = records.golds.get(&level).copied().unwrap_or(level_time);
+                        if level_time <= gold {
+                            ui.colored_label(egui::Color32::GOLD, format!("{:.1}", level_time));
+                        } else {
+                            ui.label(format!("{:.1}", level_time));
+                        }
+                        ui.label(format!("BEST {:.1}", gold));
+                        ui.end_row();
+                    }
+                });
+                ui.label(format!("DEATHS {}", run_stats.deaths));
+                ui.label(format!(
+                    "ACCURACY {:.0}% ({}/{})",
+                    run_stats.accuracy() * 100.0,
+                    run_stats.shots_hit,
+                    run_stats.shots_fired
+                ));
+                if ui.button("RESTART").clicked() {
+                    new_game.send(NewGame);
+                }
+                if ui.button("MAIN MENU").clicked() {
+                    next_app_state.set(AppState::MainMenu);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(lss) = records.to_livesplit() {
+                    if ui.button("EXPORT SPLITS").clicked() {
+                        *exported_to = crate::storage::export("traverse.lss", &lss)
+                            .map(|path| path.display().to_string());
+                    }
+                    if let Some(path) = &*exported_to {
+                        ui.label(format!("SAVED TO {}", path));
+                    }
+                }
+            })
+        });
+}
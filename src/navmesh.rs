@@ -0,0 +1,792 @@
+use std::{cmp::Ordering, collections::BinaryHeap, ops::Range};
+
+use bevy::{
+    math::{ivec2, vec2},
+    prelude::*,
+    render::mesh::{Indices, VertexAttributeValues},
+    transform::TransformSystem,
+    utils::HashMap,
+};
+use bevy_rapier3d::prelude::*;
+
+/// Steeper triangles aren't walked on, cos of 45 degrees
+const MAX_SLOPE_COS: f32 = 0.707;
+/// Polygons are bucketed into square cells this wide to find them by position
+const CELL_SIZE: f32 = 4.0;
+/// How far off the navmesh a path can start or end
+const LOCATE_DISTANCE: f32 = 4.0;
+/// Vertices closer than this are treated as the same when joining triangles
+const WELD_DISTANCE: f32 = 0.01;
+/// Level geometry is rasterized into square columns this wide. Walkable space is shrunk by a
+/// column next to anything units can't walk through, about a unit's radius
+const COLUMN_SIZE: f32 = 0.3;
+/// Headroom units need above the floor
+const AGENT_HEIGHT: f32 = 2.0;
+/// Floors closer than this in height are connected, units step up and down it
+const MAX_CLIMB: f32 = 0.4;
+/// Neighboring columns closer than this in height are merged into one polygon
+const MERGE_HEIGHT: f32 = 0.05;
+
+pub struct NavMeshPlugin;
+impl Plugin for NavMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavMesh>().add_system(
+            build_navmesh
+                .in_base_set(CoreSet::PostUpdate)
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Meshes under a `navmesh` node in a physics scene. When there are any the navmesh is built
+/// from them alone, otherwise it's built from the level's colliders
+#[derive(Component)]
+pub struct NavMeshSource;
+
+struct NavPolygon {
+    /// Convex, wound counter clockwise seen from above
+    vertices: Vec<Vec3>,
+    portals: Vec<Portal>,
+    center: Vec3,
+}
+
+/// The edge, or part of one, that a polygon shares with a neighbor
+struct Portal {
+    neighbor: usize,
+    a: Vec3,
+    b: Vec3,
+}
+
+/// Walkable polygons and how they connect, for finding paths around level geometry
+#[derive(Resource, Default)]
+pub struct NavMesh {
+    polygons: Vec<NavPolygon>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl NavMesh {
+    /// Keeps the triangles that are flat enough to walk on, as they are. Triangles wound
+    /// counter clockwise seen from above face up
+    pub fn from_triangles(triangles: impl IntoIterator<Item = [Vec3; 3]>) -> NavMesh {
+        let mut navmesh = NavMesh::default();
+        let mut edges: HashMap<(IVec3, IVec3), usize> = HashMap::default();
+        for vertices in triangles {
+            let normal = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+            if normal.length_squared() < f32::EPSILON || normal.normalize().y < MAX_SLOPE_COS {
+                continue;
+            }
+            let index = navmesh.add_polygon(vertices.to_vec());
+            for edge in 0..3 {
+                let (a, b) = (vertices[edge], vertices[(edge + 1) % 3]);
+                let (key_a, key_b) = (weld_key(a), weld_key(b));
+                let key = if (key_a.x, key_a.y, key_a.z) < (key_b.x, key_b.y, key_b.z) {
+                    (key_a, key_b)
+                } else {
+                    (key_b, key_a)
+                };
+                match edges.get(&key) {
+                    Some(&other) => {
+                        navmesh.add_portal(index, other, a, b);
+                        navmesh.add_portal(other, index, a, b);
+                    }
+                    None => {
+                        edges.insert(key, index);
+                    }
+                }
+            }
+        }
+        navmesh
+    }
+
+    /// Walkable space on level geometry, given as triangles in any order. Floors need to be
+    /// flat enough and have headroom, so the floor under obstacles is cut out. What's left is
+    /// shrunk away from walls, obstacles and drops so units keep clear of them
+    pub fn from_geometry(triangles: impl IntoIterator<Item = [Vec3; 3]>) -> NavMesh {
+        let floors = Floors::walkable(&triangles.into_iter().collect::<Vec<_>>());
+        // Merged into rectangles, so big floors don't need a polygon per column
+        let mut navmesh = NavMesh::default();
+        let mut owners = vec![usize::MAX; floors.heights.len()];
+        let mut rectangles = Vec::new();
+        for z in 0..floors.size.y {
+            for x in 0..floors.size.x {
+                let key = floors.min + ivec2(x, z);
+                for floor in floors.column(key) {
+                    if owners[floor] != usize::MAX {
+                        continue;
+                    }
+                    let height = floors.heights[floor];
+                    let free = |owners: &[usize], key: IVec2| {
+                        floors
+                            .near(key, height, MERGE_HEIGHT)
+                            .filter(|floor| owners[*floor] == usize::MAX)
+                    };
+                    let mut width = 1;
+                    while free(&owners, key + ivec2(width, 0)).is_some() {
+                        width += 1;
+                    }
+                    let mut depth = 1;
+                    while (0..width).all(|x| free(&owners, key + ivec2(x, depth)).is_some()) {
+                        depth += 1;
+                    }
+                    let index = navmesh.polygons.len();
+                    let mut total = 0.0;
+                    for z in 0..depth {
+                        for x in 0..width {
+                            let floor = free(&owners, key + ivec2(x, z)).unwrap();
+                            owners[floor] = index;
+                            total += floors.heights[floor];
+                        }
+                    }
+                    let y = total / (width * depth) as f32;
+                    let min = key.as_vec2() * COLUMN_SIZE;
+                    let max = (key + ivec2(width, depth)).as_vec2() * COLUMN_SIZE;
+                    navmesh.add_polygon(vec![
+                        Vec3::new(min.x, y, min.y),
+                        Vec3::new(min.x, y, max.y),
+                        Vec3::new(max.x, y, max.y),
+                        Vec3::new(max.x, y, min.y),
+                    ]);
+                    rectangles.push((key, ivec2(width, depth)));
+                }
+            }
+        }
+
+        // Portals are where the columns along a rectangle's sides are connected to another's.
+        // Sides are -x, +z, +x and -z
+        let mut spans: HashMap<(usize, usize, usize), (i32, i32)> = HashMap::default();
+        for z in 0..floors.size.y {
+            for x in 0..floors.size.x {
+                let column = floors.min + ivec2(x, z);
+                for floor in floors.column(column) {
+                    let index = owners[floor];
+                    let (start, size) = rectangles[index];
+                    let end = start + size - IVec2::ONE;
+                    for (side, offset, on_side) in [
+                        (0, ivec2(-1, 0), column.x == start.x),
+                        (1, ivec2(0, 1), column.y == end.y),
+                        (2, ivec2(1, 0), column.x == end.x),
+                        (3, ivec2(0, -1), column.y == start.y),
+                    ] {
+                        if !on_side {
+                            continue;
+                        }
+                        let other =
+                            match floors.near(column + offset, floors.heights[floor], MAX_CLIMB) {
+                                Some(neighbor) if owners[neighbor] != index => owners[neighbor],
+                                _ => continue,
+                            };
+                        let along = if side % 2 == 0 { column.y } else { column.x };
+                        let span = spans.entry((index, other, side)).or_insert((along, along));
+                        *span = (span.0.min(along), span.1.max(along));
+                    }
+                }
+            }
+        }
+        for ((index, other, side), (first, last)) in spans {
+            let (start, size) = rectangles[index];
+            let min = start.as_vec2() * COLUMN_SIZE;
+            let max = (start + size).as_vec2() * COLUMN_SIZE;
+            let (first, last) = (first as f32 * COLUMN_SIZE, (last + 1) as f32 * COLUMN_SIZE);
+            let (a, b) = match side {
+                0 => (vec2(min.x, first), vec2(min.x, last)),
+                1 => (vec2(first, max.y), vec2(last, max.y)),
+                2 => (vec2(max.x, first), vec2(max.x, last)),
+                _ => (vec2(first, min.y), vec2(last, min.y)),
+            };
+            let y = navmesh.polygons[index].center.y;
+            navmesh.add_portal(index, other, Vec3::new(a.x, y, a.y), Vec3::new(b.x, y, b.y));
+        }
+        navmesh
+    }
+
+    fn add_polygon(&mut self, vertices: Vec<Vec3>) -> usize {
+        let index = self.polygons.len();
+        let min = vertices
+            .iter()
+            .fold(Vec3::splat(f32::INFINITY), |min, v| min.min(*v));
+        let max = vertices
+            .iter()
+            .fold(Vec3::splat(f32::NEG_INFINITY), |max, v| max.max(*v));
+        for x in cell(min.x)..=cell(max.x) {
+            for z in cell(min.z)..=cell(max.z) {
+                self.cells.entry((x, z)).or_default().push(index);
+            }
+        }
+        let center = vertices.iter().sum::<Vec3>() / vertices.len() as f32;
+        self.polygons.push(NavPolygon {
+            vertices,
+            portals: Vec::new(),
+            center,
+        });
+        index
+    }
+
+    fn add_portal(&mut self, from: usize, to: usize, a: Vec3, b: Vec3) {
+        self.polygons[from]
+            .portals
+            .push(Portal { neighbor: to, a, b });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polygons.is_empty()
+    }
+
+    /// The polygon closest to `point` and the closest point on it
+    fn locate(&self, point: Vec3) -> Option<(usize, Vec3)> {
+        let rings = (LOCATE_DISTANCE / CELL_SIZE).ceil() as i32;
+        let (x, z) = (cell(point.x), cell(point.z));
+        let mut closest: Option<(usize, Vec3)> = None;
+        for cx in x - rings..=x + rings {
+            for cz in z - rings..=z + rings {
+                for &index in self.cells.get(&(cx, cz)).into_iter().flatten() {
+                    let on_polygon =
+                        closest_point_on_polygon(point, &self.polygons[index].vertices);
+                    let closer = closest.map_or(true, |(_, best)| {
+                        on_polygon.distance_squared(point) < best.distance_squared(point)
+                    });
+                    if closer {
+                        closest = Some((index, on_polygon));
+                    }
+                }
+            }
+        }
+        closest.filter(|(_, on_polygon)| on_polygon.distance(point) <= LOCATE_DISTANCE)
+    }
+
+    /// Waypoints from `start` to the closest point on the navmesh to `goal`, not including
+    /// `start`. None if either end is off the navmesh or they aren't connected
+    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Option<Vec<Vec3>> {
+        let (start_index, start) = self.locate(start)?;
+        let (goal_index, goal) = self.locate(goal)?;
+
+        // A* over the polygons, between their centers
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<usize, usize> = HashMap::default();
+        let mut costs: HashMap<usize, f32> = HashMap::default();
+        open.push(Open {
+            estimate: 0.0,
+            index: start_index,
+        });
+        costs.insert(start_index, 0.0);
+        while let Some(Open { index, .. }) = open.pop() {
+            if index == goal_index {
+                break;
+            }
+            let polygon = &self.polygons[index];
+            for Portal { neighbor, .. } in &polygon.portals {
+                let cost = costs[&index] + polygon.center.distance(self.polygons[*neighbor].center);
+                if costs.get(neighbor).map_or(true, |&known| cost < known) {
+                    costs.insert(*neighbor, cost);
+                    came_from.insert(*neighbor, index);
+                    open.push(Open {
+                        estimate: cost + self.polygons[*neighbor].center.distance(goal),
+                        index: *neighbor,
+                    });
+                }
+            }
+        }
+        if start_index != goal_index && !came_from.contains_key(&goal_index) {
+            return None;
+        }
+
+        let mut corridor = vec![goal_index];
+        while let Some(&previous) = came_from.get(corridor.last().unwrap()) {
+            corridor.push(previous);
+        }
+        corridor.reverse();
+
+        let mut portals = vec![(start, start)];
+        for pair in corridor.windows(2) {
+            let from = &self.polygons[pair[0]];
+            let portal = from
+                .portals
+                .iter()
+                .find(|portal| portal.neighbor == pair[1])
+                .unwrap();
+            let (a, b) = (portal.a, portal.b);
+            // Starting on an edge, the funnel can't tell which side is which
+            if portals.len() == 1 && triarea2(start, a, b).abs() < 1e-6 {
+                continue;
+            }
+            portals.push(if triarea2(from.center, a, b) > 0.0 {
+                (a, b)
+            } else {
+                (b, a)
+            });
+        }
+        portals.push((goal, goal));
+        Some(string_pull(&portals))
+    }
+}
+
+#[derive(PartialEq)]
+struct Open {
+    estimate: f32,
+    index: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    // Reversed so the heap pops the lowest estimate
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn weld_key(vertex: Vec3) -> IVec3 {
+    (vertex / WELD_DISTANCE).round().as_ivec3()
+}
+
+fn cell(coordinate: f32) -> i32 {
+    (coordinate / CELL_SIZE).floor() as i32
+}
+
+/// Twice the signed area of the triangle seen from above
+fn triarea2(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    (c.x - a.x) * (b.z - a.z) - (b.x - a.x) * (c.z - a.z)
+}
+
+/// Floors in a grid of square columns, a column can have several at different heights
+struct Floors {
+    /// The first column
+    min: IVec2,
+    size: IVec2,
+    /// Where each column's floors start in `heights`, and where the last one's end
+    starts: Vec<usize>,
+    /// Sorted by height within each column
+    heights: Vec<f32>,
+}
+
+impl Floors {
+    /// Floors flat enough to walk on with headroom above them, shrunk by a column next to
+    /// anything units can't walk through or onto
+    fn walkable(triangles: &[[Vec3; 3]]) -> Floors {
+        let walkable = |triangle: &[Vec3; 3]| {
+            let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+            normal.length_squared() >= f32::EPSILON && normal.normalize().y >= MAX_SLOPE_COS
+        };
+        let (min, max) = triangles
+            .iter()
+            .filter(|triangle| walkable(triangle))
+            .flatten()
+            .fold(
+                (IVec2::splat(i32::MAX), IVec2::splat(i32::MIN)),
+                |(min, max), vertex| {
+                    let key = column(*vertex);
+                    (min.min(key), max.max(key))
+                },
+            );
+        if min.x > max.x {
+            return Floors {
+                min: IVec2::ZERO,
+                size: IVec2::ZERO,
+                starts: vec![0],
+                heights: Vec::new(),
+            };
+        }
+        let size = max - min + IVec2::ONE;
+        let mut columns = vec![Vec::new(); (size.x * size.y) as usize];
+        for triangle in triangles.iter().filter(|triangle| walkable(triangle)) {
+            for (key, square_min, square_max) in overlapped_columns(triangle) {
+                let center = (square_min + square_max) / 2.0;
+                if triangle_contains(triangle, center) {
+                    let index = ((key.y - min.y) * size.x + key.x - min.x) as usize;
+                    columns[index].push(plane_height(triangle, center));
+                }
+            }
+        }
+        for heights in &mut columns {
+            heights.sort_by(f32::total_cmp);
+            // Something just above a floor, like a rug, is walked on instead
+            heights.dedup_by(|height, last| {
+                let merge = *height - *last < MAX_CLIMB;
+                if merge {
+                    *last = *height;
+                }
+                merge
+            });
+        }
+        let mut floors = Floors::from_columns(min, size, columns);
+
+        // Anything in the way of a unit standing on the floor cuts it out
+        let mut blocked = vec![false; floors.heights.len()];
+        for triangle in triangles {
+            let low = triangle[0].y.min(triangle[1].y).min(triangle[2].y);
+            let high = triangle[0].y.max(triangle[1].y).max(triangle[2].y);
+            let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+            for (key, square_min, square_max) in overlapped_columns(triangle) {
+                let column = floors.column(key);
+                let blocks = |bottom: f32, top: f32, floor: usize| {
+                    let height = floors.heights[floor];
+                    top > height + MAX_CLIMB && bottom < height + AGENT_HEIGHT
+                };
+                // Most triangles are floors that can't block anything
+                if !column.clone().any(|floor| blocks(low, high, floor))
+                    || !triangle_overlaps_square(triangle, square_min, square_max)
+                {
+                    continue;
+                }
+                // Where the triangle is in this column, walls take up all of their height
+                let (bottom, top) = if normal.y.abs() < 0.1 * normal.length() {
+                    (low, high)
+                } else {
+                    [
+                        square_min,
+                        vec2(square_min.x, square_max.y),
+                        square_max,
+                        vec2(square_max.x, square_min.y),
+                    ]
+                    .into_iter()
+                    .map(|corner| plane_height(triangle, corner).clamp(low, high))
+                    .fold(
+                        (f32::INFINITY, f32::NEG_INFINITY),
+                        |(bottom, top), height| (bottom.min(height), top.max(height)),
+                    )
+                };
+                for floor in column {
+                    if blocks(bottom, top, floor) {
+                        blocked[floor] = true;
+                    }
+                }
+            }
+        }
+        floors = floors.retain(|floor, _| !blocked[floor]);
+
+        // Units keep a column away from walls, obstacles and drops
+        floors.retain(|floor, key| {
+            let height = floors.heights[floor];
+            (-1..=1).all(|x| {
+                (-1..=1).all(|z| floors.near(key + ivec2(x, z), height, MAX_CLIMB).is_some())
+            })
+        })
+    }
+
+    fn from_columns(min: IVec2, size: IVec2, columns: Vec<Vec<f32>>) -> Floors {
+        let mut starts = Vec::with_capacity(columns.len() + 1);
+        let mut heights = Vec::new();
+        for column in columns {
+            starts.push(heights.len());
+            heights.extend(column);
+        }
+        starts.push(heights.len());
+        Floors {
+            min,
+            size,
+            starts,
+            heights,
+        }
+    }
+
+    /// The floors that `keep` returns true for, given each floor's index and column
+    fn retain(&self, keep: impl Fn(usize, IVec2) -> bool) -> Floors {
+        let mut starts = Vec::with_capacity(self.starts.len());
+        let mut heights = Vec::with_capacity(self.heights.len());
+        for z in 0..self.size.y {
+            for x in 0..self.size.x {
+                let key = self.min + ivec2(x, z);
+                starts.push(heights.len());
+                heights.extend(
+                    self.column(key)
+                        .filter(|floor| keep(*floor, key))
+                        .map(|floor| self.heights[floor]),
+                );
+            }
+        }
+        starts.push(heights.len());
+        Floors {
+            min: self.min,
+            size: self.size,
+            starts,
+            heights,
+        }
+    }
+
+    /// The indices of the column's floors, empty outside the grid
+    fn column(&self, key: IVec2) -> Range<usize> {
+        let local = key - self.min;
+        if local.x < 0 || local.y < 0 || local.x >= self.size.x || local.y >= self.size.y {
+            return 0..0;
+        }
+        let index = (local.y * self.size.x + local.x) as usize;
+        self.starts[index]..self.starts[index + 1]
+    }
+
+    /// The column's floor closest to `height`, if it's within `tolerance`
+    fn near(&self, key: IVec2, height: f32, tolerance: f32) -> Option<usize> {
+        self.column(key)
+            .map(|floor| (floor, (self.heights[floor] - height).abs()))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(floor, _)| floor)
+    }
+}
+
+fn column(point: Vec3) -> IVec2 {
+    ivec2(
+        (point.x / COLUMN_SIZE).floor() as i32,
+        (point.z / COLUMN_SIZE).floor() as i32,
+    )
+}
+
+/// The columns in the triangle's bounds, and each one's corners
+fn overlapped_columns(triangle: &[Vec3; 3]) -> impl Iterator<Item = (IVec2, Vec2, Vec2)> {
+    let min = column(triangle[0].min(triangle[1]).min(triangle[2]));
+    let max = column(triangle[0].max(triangle[1]).max(triangle[2]));
+    (min.y..=max.y).flat_map(move |z| {
+        (min.x..=max.x).map(move |x| {
+            let square_min = ivec2(x, z).as_vec2() * COLUMN_SIZE;
+            (ivec2(x, z), square_min, square_min + COLUMN_SIZE)
+        })
+    })
+}
+
+/// Height of the triangle's plane above a point
+fn plane_height([a, b, c]: &[Vec3; 3], point: Vec2) -> f32 {
+    let normal = (*b - *a).cross(*c - *a);
+    a.y - (normal.x * (point.x - a.x) + normal.z * (point.y - a.z)) / normal.y
+}
+
+/// Whether `point` is inside the triangle seen from above
+fn triangle_contains([a, b, c]: &[Vec3; 3], point: Vec2) -> bool {
+    let point = Vec3::new(point.x, 0.0, point.y);
+    let areas = [
+        triarea2(*a, *b, point),
+        triarea2(*b, *c, point),
+        triarea2(*c, *a, point),
+    ];
+    areas.iter().all(|area| *area >= 0.0) || areas.iter().all(|area| *area <= 0.0)
+}
+
+/// Whether the triangle seen from above overlaps the square, by separating axes
+fn triangle_overlaps_square(triangle: &[Vec3; 3], min: Vec2, max: Vec2) -> bool {
+    let points = triangle.map(|vertex| vec2(vertex.x, vertex.z));
+    let corners = [min, vec2(min.x, max.y), max, vec2(max.x, min.y)];
+    (0..3).all(|edge| {
+        let a = points[edge];
+        let b = points[(edge + 1) % 3];
+        let axis = (b - a).perp();
+        let project = |point: &Vec2| axis.dot(*point);
+        let (triangle_min, triangle_max) = points
+            .iter()
+            .map(project)
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), d| {
+                (low.min(d), high.max(d))
+            });
+        let (square_min, square_max) = corners
+            .iter()
+            .map(project)
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), d| {
+                (low.min(d), high.max(d))
+            });
+        triangle_max >= square_min && square_max >= triangle_min
+    })
+}
+
+/// The shortest path through the portals between the corridor's triangles, the simple
+/// stupid funnel algorithm. The first and last portals are the start and end points
+fn string_pull(portals: &[(Vec3, Vec3)]) -> Vec<Vec3> {
+    let same = |a: Vec3, b: Vec3| a.distance_squared(b) < 1e-6;
+    let mut path = Vec::new();
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut left_index, mut right_index) = (0, 0);
+    let mut i = 1;
+    while i < portals.len() {
+        let (new_left, new_right) = portals[i];
+
+        if triarea2(apex, right, new_right) <= 0.0 {
+            if same(apex, right) || triarea2(apex, left, new_right) > 0.0 {
+                right = new_right;
+                right_index = i;
+            } else {
+                // Right crossed over left, the left point is a corner
+                path.push(left);
+                apex = left;
+                let apex_index = left_index;
+                (left, right) = (apex, apex);
+                (left_index, right_index) = (apex_index, apex_index);
+                i = apex_index + 1;
+                continue;
+            }
+        }
+
+        if triarea2(apex, left, new_left) >= 0.0 {
+            if same(apex, left) || triarea2(apex, right, new_left) < 0.0 {
+                left = new_left;
+                left_index = i;
+            } else {
+                path.push(right);
+                apex = right;
+                let apex_index = right_index;
+                (left, right) = (apex, apex);
+                (left_index, right_index) = (apex_index, apex_index);
+                i = apex_index + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+    let end = portals[portals.len() - 1].0;
+    if path.last().map_or(true, |last| !same(*last, end)) {
+        path.push(end);
+    }
+    path
+}
+
+/// Split into a fan of triangles
+fn closest_point_on_polygon(p: Vec3, vertices: &[Vec3]) -> Vec3 {
+    (1..vertices.len() - 1)
+        .map(|i| closest_point_on_triangle(p, &[vertices[0], vertices[i], vertices[i + 1]]))
+        .min_by(|a, b| a.distance_squared(p).total_cmp(&b.distance_squared(p)))
+        .unwrap()
+}
+
+/// From Real-Time Collision Detection, 5.1.5
+fn closest_point_on_triangle(p: Vec3, [a, b, c]: &[Vec3; 3]) -> Vec3 {
+    let (a, b, c) = (*a, *b, *c);
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// Rebuilds the navmesh whenever fixed colliders or navmesh sources are added, after
+/// transforms are propagated so new ones are in the right place
+pub fn build_navmesh(
+    mut navmesh: ResMut<NavMesh>,
+    added_colliders: Query<&RigidBody, Added<Collider>>,
+    added_sources: Query<(), Added<NavMeshSource>>,
+    colliders: Query<(&Collider, &RigidBody, &GlobalTransform), Without<Sensor>>,
+    sources: Query<(&Handle<Mesh>, &GlobalTransform), With<NavMeshSource>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    let fixed_added = added_colliders
+        .iter()
+        .any(|body| matches!(body, RigidBody::Fixed));
+    if !fixed_added && added_sources.is_empty() {
+        return;
+    }
+    let mut triangles = Vec::new();
+    *navmesh = if sources.is_empty() {
+        for (collider, body, transform) in &colliders {
+            if matches!(body, RigidBody::Fixed) {
+                collider_triangles(collider, transform, &mut triangles);
+            }
+        }
+        NavMesh::from_geometry(triangles)
+    } else {
+        // Authored navmeshes are walked as they are
+        for (mesh, transform) in &sources {
+            if let Some(mesh) = meshes.get(mesh) {
+                mesh_triangles(mesh, transform, &mut triangles);
+            }
+        }
+        NavMesh::from_triangles(triangles)
+    };
+    info!("Built navmesh with {} polygons", navmesh.polygons.len());
+}
+
+/// Trimeshes and boxes
+fn collider_triangles(
+    collider: &Collider,
+    transform: &GlobalTransform,
+    triangles: &mut Vec<[Vec3; 3]>,
+) {
+    // Rapier scales the shape a frame after the collider is added, so whatever scale it has so
+    // far is taken out and the transform's applied instead
+    let scale = collider.scale();
+    let to_world = |v: Vec3| transform.transform_point(v / scale);
+    if let Some(trimesh) = collider.as_trimesh() {
+        let vertices = trimesh.raw.vertices();
+        for [a, b, c] in trimesh.raw.indices() {
+            let [a, b, c] = [a, b, c].map(|i| {
+                let v = vertices[*i as usize];
+                to_world(Vec3::new(v.x, v.y, v.z))
+            });
+            triangles.push([a, b, c]);
+        }
+    } else if let Some(cuboid) = collider.as_cuboid() {
+        let half = cuboid.half_extents();
+        // Each face's normal, and two sides whose cross product is the normal so the face is
+        // wound to face out
+        for (normal, u, v) in [
+            (Vec3::Y, Vec3::Z, Vec3::X),
+            (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+            (Vec3::X, Vec3::Y, Vec3::Z),
+            (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+            (Vec3::Z, Vec3::X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::Y, Vec3::X),
+        ] {
+            let (center, u, v) = (normal * half, u * half, v * half);
+            let corners = [
+                center - u - v,
+                center + u - v,
+                center + u + v,
+                center - u + v,
+            ]
+            .map(to_world);
+            triangles.push([corners[0], corners[1], corners[2]]);
+            triangles.push([corners[0], corners[2], corners[3]]);
+        }
+    }
+}
+
+fn mesh_triangles(mesh: &Mesh, transform: &GlobalTransform, triangles: &mut Vec<[Vec3; 3]>) {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions,
+        _ => return,
+    };
+    let positions: Vec<Vec3> = positions
+        .iter()
+        .map(|p| transform.transform_point(Vec3::from(*p)))
+        .collect();
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|i| *i as usize).collect(),
+        Some(Indices::U32(indices)) => indices.iter().map(|i| *i as usize).collect(),
+        None => (0..positions.len()).collect(),
+    };
+    for triangle in indices.chunks_exact(3) {
+        triangles.push([
+            positions[triangle[0]],
+            positions[triangle[1]],
+            positions[triangle[2]],
+        ]);
+    }
+}
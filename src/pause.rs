@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use bevy_fps_controller::controller::FpsController;
+use bevy_rapier3d::prelude::RapierConfiguration;
+
+use crate::{AppState, ClientSet, GameLoading};
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<PauseState>()
+            .init_resource::<SettingClock>()
+            .add_system(
+                update_pause
+                    .after(ClientSet::Cursor)
+                    .run_if(in_state(GameLoading::Loaded)),
+            )
+            .add_system(pause_world.in_schedule(OnEnter(PauseState::Paused)))
+            .add_system(resume_world.in_schedule(OnExit(PauseState::Paused)));
+    }
+}
+
+/// Gameplay systems only run while the game is Running. Paused whenever the menu is open
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// The clock's code entry is open
+#[derive(Resource, Default)]
+pub struct SettingClock(pub bool);
+
+/// Input is disabled while the cursor is free. Setting the clock doesn't pause, drones can
+/// still get you while you're entering a code
+pub fn update_pause(
+    fps_controller: Query<&FpsController>,
+    setting_clock: Res<SettingClock>,
+    app_state: Res<State<AppState>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let input_disabled = fps_controller
+        .iter()
+        .any(|controller| !controller.enable_input);
+    let menu_open = app_state.0 != AppState::InGame || (input_disabled && !setting_clock.0);
+    let paused = pause_state.0 == PauseState::Paused;
+    if menu_open != paused {
+        next_pause_state.set(if menu_open {
+            PauseState::Paused
+        } else {
+            PauseState::Running
+        });
+    }
+}
+
+fn pause_world(
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+) {
+    rapier_config.physics_pipeline_active = false;
+    for mut animation_player in &mut animation_players {
+        animation_player.pause();
+    }
+}
+
+fn resume_world(
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+) {
+    rapier_config.physics_pipeline_active = true;
+    for mut animation_player in &mut animation_players {
+        animation_player.resume();
+    }
+}
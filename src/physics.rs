@@ -1,4 +1,4 @@
-use crate::util::all_children;
+use crate::{navmesh::NavMeshSource, util::all_children};
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
@@ -14,16 +14,38 @@ impl Plugin for PhysicsStuff {
 #[derive(Component)]
 pub struct AddTrimeshPhysics;
 
+/// A node named `navmesh`, or a mesh under one
+fn is_navmesh(entity: Entity, names: &Query<&Name>, parents: &Query<&Parent>) -> bool {
+    let is_named = |entity| {
+        names
+            .get(entity)
+            .map_or(false, |name| name.to_lowercase().starts_with("navmesh"))
+    };
+    is_named(entity)
+        || parents
+            .get(entity)
+            .map_or(false, |parent| is_named(parent.get()))
+}
+
 pub fn setup_trimeshe_colliders(
     mut commands: Commands,
     scene_entities: Query<Entity, With<AddTrimeshPhysics>>,
     children_query: Query<&Children>,
     mesh_handles: Query<&Handle<Mesh>>,
     meshes: Res<Assets<Mesh>>,
+    names: Query<&Name>,
+    parents: Query<&Parent>,
 ) {
     for entity in scene_entities.iter() {
         if let Ok(children) = children_query.get(entity) {
             all_children(children, &children_query, &mut |entity| {
+                if mesh_handles.contains(entity) && is_navmesh(entity, &names, &parents) {
+                    // Only walked on, not collided with or seen
+                    commands
+                        .entity(entity)
+                        .insert((NavMeshSource, Visibility::Hidden));
+                    return;
+                }
                 if let Ok(mesh_h) = mesh_handles.get_component(entity) {
                     let mesh = meshes.get(mesh_h).unwrap();
                     // TODO seems inefficient if there are multiple instances of the same trimesh collider
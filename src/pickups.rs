@@ -0,0 +1,174 @@
+use bevy::{gltf::GltfExtras, prelude::*};
+use bevy_fps_controller::controller::RenderPlayer;
+use serde::Deserialize;
+
+use crate::{
+    assets::PropAssets,
+    pause::PauseState,
+    player::PlayerGun,
+    sfx::{PlaySfx, Sfx},
+    triggers::{detect_triggers, trigger_sensor, PlayerTriggers, TriggerKind},
+    util::all_children,
+    GameLoading, Health,
+};
+
+pub struct PickupsPlugin;
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_pickups.run_if(in_state(GameLoading::Loaded)))
+            .add_systems(
+                (spin_pickups, collect_pickups.after(detect_triggers))
+                    .distributive_run_if(in_state(GameLoading::Loaded))
+                    .distributive_run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PickupKind {
+    /// Amount is in magazines, for every carried weapon
+    Ammo,
+    Health,
+}
+
+impl PickupKind {
+    fn default_amount(&self) -> f32 {
+        match self {
+            PickupKind::Ammo => 1.0,
+            PickupKind::Health => 0.25,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub amount: f32,
+}
+
+/// Scan the children of this scene for pickup nodes
+#[derive(Component)]
+pub struct PickupSpawns;
+
+/// The visible part of a pickup
+#[derive(Component)]
+pub struct PickupModel;
+
+const PICKUP_RADIUS: f32 = 1.0;
+
+#[derive(Deserialize)]
+struct PickupExtras {
+    amount: Option<f32>,
+}
+
+/// Nodes are named like `pickup.ammo` or `pickup.health.001`, an `amount` extra overrides the
+/// default amount
+fn pickup_kind(name: &str) -> Option<PickupKind> {
+    let name = name.to_lowercase();
+    let mut parts = name.split('.');
+    if parts.next() != Some("pickup") {
+        return None;
+    }
+    match parts.next() {
+        Some("ammo") => Some(PickupKind::Ammo),
+        Some("health") => Some(PickupKind::Health),
+        kind => {
+            warn!("Unknown pickup kind {kind:?} in {name}");
+            None
+        }
+    }
+}
+
+pub fn spawn_pickups(
+    mut commands: Commands,
+    scene_entities: Query<Entity, With<PickupSpawns>>,
+    children_query: Query<&Children>,
+    nodes: Query<(&Name, Option<&GltfExtras>)>,
+    props: Res<PropAssets>,
+) {
+    for entity in scene_entities.iter() {
+        if let Ok(children) = children_query.get(entity) {
+            all_children(children, &children_query, &mut |entity| {
+                if let Ok((name, extras)) = nodes.get(entity) {
+                    let kind = match pickup_kind(name) {
+                        Some(kind) => kind,
+                        None => return,
+                    };
+                    let amount = extras
+                        .and_then(|extras| serde_json::from_str::<PickupExtras>(&extras.value).ok())
+                        .and_then(|extras| extras.amount)
+                        .unwrap_or(kind.default_amount());
+                    let scene = match kind {
+                        PickupKind::Ammo => props.projectile.clone(),
+                        PickupKind::Health => props.projectile_lite_red.clone(),
+                    };
+                    commands
+                        .entity(entity)
+                        .insert(Pickup { kind, amount })
+                        .insert(trigger_sensor(TriggerKind::Pickup, PICKUP_RADIUS))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                SceneBundle {
+                                    scene,
+                                    transform: Transform::from_xyz(0.0, 0.5, 0.0),
+                                    ..default()
+                                },
+                                PickupModel,
+                            ));
+                        });
+                }
+            });
+            commands.entity(entity).remove::<PickupSpawns>();
+        }
+    }
+}
+
+fn spin_pickups(time: Res<Time>, mut models: Query<&mut Transform, With<PickupModel>>) {
+    for mut trans in &mut models {
+        trans.rotate_y(time.delta_seconds() * 2.0);
+    }
+}
+
+/// Pickups are used up while the player stands in them, unless the player is already full.
+/// One that was left because the player was full is taken once they need it
+fn collect_pickups(
+    mut commands: Commands,
+    player_triggers: Res<PlayerTriggers>,
+    pickups: Query<&Pickup>,
+    mut player: Query<(&mut Health, Option<&mut PlayerGun>), With<RenderPlayer>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    for (trigger, kind) in &player_triggers.0 {
+        if *kind != TriggerKind::Pickup {
+            continue;
+        }
+        let pickup = match pickups.get(*trigger) {
+            Ok(pickup) => pickup,
+            Err(_) => continue,
+        };
+        for (mut health, gun) in &mut player {
+            let used = match pickup.kind {
+                PickupKind::Ammo => match gun {
+                    Some(mut gun) => {
+                        // Every weapon gets some, not just the first that isn't full
+                        let mut used = false;
+                        for carried in &mut gun.weapons {
+                            used |= carried.add_magazines(pickup.amount);
+                        }
+                        used
+                    }
+                    None => false,
+                },
+                PickupKind::Health if health.0 < 1.0 => {
+                    health.0 = (health.0 + pickup.amount).min(1.0);
+                    true
+                }
+                PickupKind::Health => false,
+            };
+            if used {
+                commands.entity(*trigger).despawn_recursive();
+                sfx.send(PlaySfx(Sfx::Pickup));
+            }
+        }
+    }
+}
@@ -1,37 +1,97 @@
 use crate::{
-    assets::{AudioAssets, PropAssets},
-    character_controller::LogicalPlayerEntity,
-    levels::GameLevel,
+    assets::PropAssets,
+    character_controller::{LogicalPlayerEntity, JUMP_SPEED},
+    damage::{DamageEvent, DamageKind, DamageSet, Damaged, Died},
+    input::{Action, ActionState, UiWantsPointer},
+    levels::{
+        manifest::{LevelProperties, Levels},
+        GameLevel,
+    },
     materials::pbr_material::{EnvSettings, MaterialsSet},
-    ui::{ui_system, AudioVolumes},
-    units::UnitData,
-    GameLoading, GameRng, Health, LevelsStarted,
+    pause::PauseState,
+    sfx::{PlaySfx, Sfx},
+    triggers::{PlayerSpawn, TriggerEntered, TriggerKind},
+    units::{DamagePlayer, UnitData},
+    weapons::{WeaponDef, Weapons},
+    AppState, ClientSet, GameLoading, GameRng, Health,
+};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use bevy::{input::mouse::MouseWheel, math::vec3, prelude::*};
+use bevy_fps_controller::controller::{
+    fps_controller_input, fps_controller_render, FpsController, FpsControllerInput, RenderPlayer,
 };
-use bevy::{math::vec3, prelude::*};
-use bevy_egui::EguiContexts;
-use bevy_fps_controller::controller::{FpsController, RenderPlayer};
 
-use bevy_kira_audio::AudioControl;
 use bevy_rapier3d::prelude::*;
-use rand::seq::SliceRandom;
+use rand::Rng;
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            (
-                respawn,
-                player_shoot,
-                add_gun,
-                add_crosshair,
-                progress_projectiles,
-                gun_visibility,
+        app.add_event::<TeleportPlayer>()
+            .add_event::<PlayerFired>()
+            .add_event::<ProjectileHit>()
+            .init_resource::<RunStats>()
+            .add_system(
+                teleport_player
+                    .after(ClientSet::Ui)
+                    .run_if(in_state(GameLoading::Loaded)),
             )
-                .chain()
-                .distributive_run_if(in_state(GameLoading::Loaded))
-                .before(MaterialsSet::MaterialSwap)
-                .after(ui_system),
-        );
+            .add_systems(
+                (
+                    respawn.after(DamageSet),
+                    place_at_spawn_point,
+                    switch_weapon,
+                    reload,
+                    update_spread,
+                    player_shoot,
+                    scale_crosshair,
+                    animate_reload,
+                    add_gun,
+                    add_crosshair,
+                    progress_projectiles,
+                    gun_visibility,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameLoading::Loaded))
+                    .distributive_run_if(in_state(PauseState::Running))
+                    .before(MaterialsSet::MaterialSwap)
+                    .after(ClientSet::Ui),
+            )
+            .add_systems(
+                (aim_punch.after(DamageSet), apply_kick)
+                    .chain()
+                    .after(fps_controller_input)
+                    .before(fps_controller_render)
+                    .distributive_run_if(in_state(GameLoading::Loaded))
+                    .distributive_run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+/// Move the player to the start of a level
+pub struct TeleportPlayer(pub GameLevel);
+
+/// The player shot, units in earshot go and look
+pub struct PlayerFired {
+    pub position: Vec3,
+}
+
+/// Stats for the current run, shown on the results screen
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub deaths: u32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+}
+
+impl RunStats {
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.shots_hit as f32 / self.shots_fired as f32
+        }
     }
 }
 
@@ -44,67 +104,206 @@ pub struct GunFlash;
 #[derive(Component)]
 pub struct GunModel;
 
+/// A weapon the player has, with its ammo
+pub struct CarriedWeapon {
+    pub def: WeaponDef,
+    /// Rounds loaded
+    pub magazine: u32,
+    /// Rounds carried that aren't loaded
+    pub reserve: u32,
+}
+
+impl CarriedWeapon {
+    pub fn new(def: WeaponDef) -> CarriedWeapon {
+        CarriedWeapon {
+            magazine: def.magazine,
+            reserve: def.max_reserve,
+            def,
+        }
+    }
+
+    /// Adds to the reserve, up to the max. Returns false if it was already full
+    pub fn add_magazines(&mut self, magazines: f32) -> bool {
+        if self.reserve >= self.def.max_reserve {
+            return false;
+        }
+        let rounds = (magazines * self.def.magazine as f32).ceil() as u32;
+        self.reserve = (self.reserve + rounds).min(self.def.max_reserve);
+        true
+    }
+
+    fn can_reload(&self) -> bool {
+        self.magazine < self.def.magazine && self.reserve > 0
+    }
+}
+
 #[derive(Component)]
 pub struct PlayerGun {
-    pub attack_damage: f32,
+    /// The carried weapons, in number key order
+    pub weapons: Vec<CarriedWeapon>,
+    pub current: usize,
     pub fire_cooldown: f32,
-    pub fire_rate: f32,
+    /// Seconds until the reload finishes, zero when not reloading
+    pub reloading: f32,
+    /// Degrees sustained fire has added to the spread
+    pub bloom: f32,
+    /// Degrees across the cone the next shot lands in
+    pub spread: f32,
+    /// Pitch and yaw from recoil and aim punch that haven't been applied to the view yet
+    pub kick: Vec2,
+}
+
+impl PlayerGun {
+    pub fn carried(&self) -> Option<&CarriedWeapon> {
+        self.weapons.get(self.current)
+    }
+
+    pub fn weapon(&self) -> Option<&WeaponDef> {
+        self.carried().map(|carried| &carried.def)
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reloading > 0.0
+    }
 }
 
 fn add_gun(
     mut commands: Commands,
-    props: Res<PropAssets>,
+    asset_server: Res<AssetServer>,
+    weapons: Weapons,
     player: Query<Entity, (With<LogicalPlayerEntity>, Without<GunRef>)>,
 ) {
     if let Some(player) = player.iter().next() {
-        let env_settings = EnvSettings {
-            env_spec: 0.1,
-            env_diff: 0.1,
-            emit_mult: 0.1,
+        let gun = PlayerGun {
+            weapons: weapons
+                .loadout()
+                .into_iter()
+                .map(CarriedWeapon::new)
+                .collect(),
+            current: 0,
+            fire_cooldown: 1.0,
+            reloading: 0.0,
+            bloom: 0.0,
+            spread: 0.0,
+            kick: Vec2::ZERO,
         };
-        let trans = Transform::from_translation(vec3(0.36, -0.1, -0.38));
+        let gun_ref = match gun.weapon() {
+            Some(weapon) => spawn_viewmodel(&mut commands, &asset_server, player, weapon),
+            None => {
+                warn!("The weapon loadout is empty");
+                commands.spawn_empty().id()
+            }
+        };
+        commands.entity(player).insert((GunRef(gun_ref), gun));
+    }
+}
+
+/// Spawns the weapon's gun and flash as children of the player, returns the gun
+fn spawn_viewmodel(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    player: Entity,
+    weapon: &WeaponDef,
+) -> Entity {
+    let viewmodel = &weapon.viewmodel;
+    let env_settings = EnvSettings {
+        env_spec: 0.1,
+        env_diff: 0.1,
+        emit_mult: 0.1,
+    };
+    let trans = Transform::from_translation(viewmodel.offset);
+    let gun = commands
+        .spawn(SceneBundle {
+            scene: asset_server.load(&viewmodel.scene),
+            transform: trans,
+            ..default()
+        })
+        .insert(env_settings)
+        .insert(GunModel)
+        .id();
+    commands.entity(player).add_child(gun);
+    if let Some(emit_scene) = &viewmodel.emit_scene {
         let gun_emit = commands
             .spawn(SceneBundle {
-                scene: props.gun_emit.clone(),
-                transform: trans,
-                ..default()
-            })
-            .insert(GunModel)
-            .id();
-        let gun = commands
-            .spawn(SceneBundle {
-                scene: props.gun.clone(),
+                scene: asset_server.load(emit_scene),
                 transform: trans,
                 ..default()
             })
-            .insert(env_settings)
             .insert(GunModel)
             .id();
-        let flash = commands
-            .spawn(SceneBundle {
-                scene: props.gun_flash.clone(),
-                transform: trans,
-                visibility: Visibility::Hidden,
-                ..default()
-            })
-            .insert(GunFlash)
-            .insert(EnvSettings {
-                env_spec: 0.0,
-                env_diff: 0.0,
-                emit_mult: 10.0, //not working, even if I chain and apply_system_buffers before mat swap
-            })
-            .id();
-        commands
-            .entity(player)
-            .insert(GunRef(gun))
-            .add_child(gun)
-            .add_child(gun_emit)
-            .add_child(flash)
-            .insert(PlayerGun {
-                attack_damage: 0.3,
-                fire_cooldown: 1.0,
-                fire_rate: 10.0,
-            });
+        commands.entity(player).add_child(gun_emit);
+    }
+    let flash = commands
+        .spawn(SceneBundle {
+            scene: asset_server.load(&viewmodel.flash_scene),
+            transform: Transform::from_translation(viewmodel.flash_offset),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(GunFlash)
+        .insert(EnvSettings {
+            env_spec: 0.0,
+            env_diff: 0.0,
+            emit_mult: 10.0, //not working, even if I chain and apply_system_buffers before mat swap
+        })
+        .id();
+    commands.entity(player).add_child(flash);
+    gun
+}
+
+const NUMBER_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// Number keys pick a weapon, the mouse wheel and NextWeapon cycle through them
+fn switch_weapon(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    action_state: Res<ActionState>,
+    keys: Res<Input<KeyCode>>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut player: Query<(Entity, &mut PlayerGun, &mut GunRef)>,
+    viewmodels: Query<Entity, Or<(With<GunModel>, With<GunFlash>)>>,
+) {
+    let scroll: f32 = mouse_wheel.iter().map(|wheel| wheel.y).sum();
+    for (player, mut gun, mut gun_ref) in &mut player {
+        let count = gun.weapons.len();
+        if count == 0 {
+            continue;
+        }
+        let picked = NUMBER_KEYS
+            .iter()
+            .take(count)
+            .position(|key| keys.just_pressed(*key));
+        let next = if let Some(picked) = picked {
+            picked
+        } else if scroll < 0.0 || action_state.just_pressed(Action::NextWeapon) {
+            (gun.current + 1) % count
+        } else if scroll > 0.0 {
+            (gun.current + count - 1) % count
+        } else {
+            continue;
+        };
+        if next == gun.current {
+            continue;
+        }
+        gun.current = next;
+        gun.fire_cooldown = 1.0;
+        gun.reloading = 0.0;
+        for viewmodel in &viewmodels {
+            commands.entity(viewmodel).despawn_recursive();
+        }
+        if let Some(weapon) = gun.weapon() {
+            gun_ref.0 = spawn_viewmodel(&mut commands, &asset_server, player, weapon);
+        }
     }
 }
 
@@ -132,7 +331,7 @@ fn add_crosshair(
     }
 }
 
-fn player_shoot(
+pub fn player_shoot(
     mut commands: Commands,
     rapier_context: Res<RapierContext>,
     mut player: Query<(
@@ -141,26 +340,35 @@ fn player_shoot(
         &LogicalPlayerEntity,
         &mut PlayerGun,
     )>,
-    buttons: Res<Input<MouseButton>>,
+    action_state: Res<ActionState>,
     props: Res<PropAssets>,
-    mut contexts: EguiContexts,
+    ui_wants_pointer: Res<UiWantsPointer>,
     mut gun_flash: Query<&mut Visibility, With<GunFlash>>,
-    mut healths: Query<&mut Health>,
+    healths: Query<(), With<Health>>,
     time: Res<Time>,
-    state: Res<State<GameLevel>>,
-    audio_assets: Res<AudioAssets>,
     mut rng: ResMut<GameRng>,
-    audio: Res<bevy_kira_audio::Audio>,
-    audio_volumes: Res<AudioVolumes>,
+    state: Res<State<GameLevel>>,
+    levels: Levels,
+    mut sfx: EventWriter<PlaySfx>,
+    mut run_stats: ResMut<RunStats>,
+    mut fired_events: EventWriter<PlayerFired>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
     // We will color in read the colliders hovered by the mouse.
     for (entity, camera_transform, logical_player_entity, mut gun) in &mut player {
-        gun.fire_cooldown -= gun.fire_rate * time.delta_seconds();
+        let weapon = match gun.weapon() {
+            Some(weapon) => weapon.clone(),
+            None => continue,
+        };
+        gun.fire_cooldown -= weapon.fire_rate * time.delta_seconds();
 
-        if !buttons.pressed(MouseButton::Left)
-            || contexts.ctx_mut().wants_pointer_input()
+        let empty = gun.carried().map_or(true, |carried| carried.magazine == 0);
+        if !action_state.pressed(Action::Fire)
+            || ui_wants_pointer.0
             || gun.fire_cooldown > 0.0
-            || !state.0.show_gun()
+            || empty
+            || gun.is_reloading()
+            || !levels.get(&state.0).show_gun
         {
             for mut flash in &mut gun_flash {
                 *flash = Visibility::Hidden;
@@ -172,42 +380,38 @@ fn player_shoot(
             }
         }
 
-        audio
-            .play(
-                [
-                    audio_assets.playergun1.clone(),
-                    audio_assets.playergun2.clone(),
-                    audio_assets.playergun3.clone(),
-                    audio_assets.playergun4.clone(),
-                    audio_assets.playergun5.clone(),
-                ]
-                .choose(&mut rng.0)
-                .unwrap()
-                .clone(),
-            )
-            .with_playback_rate(1.0)
-            .with_volume(audio_volumes.sfx as f64);
+        sfx.send(PlaySfx(weapon.sound));
 
         gun.fire_cooldown = 1.0;
+        let current = gun.current;
+        if let Some(carried) = gun.weapons.get_mut(current) {
+            carried.magazine -= 1;
+        }
+        run_stats.shots_fired += 1;
         // First, compute a ray from the mouse position.
         let origin = camera_transform.translation();
-        let direction = camera_transform.forward();
+        let direction = spread_direction(camera_transform, gun.spread, &mut rng);
+        gun.bloom = (gun.bloom + weapon.bloom).min(weapon.max_bloom);
+        let recoil = weapon.recoil * Vec2::new(1.0, rng.gen_range(-1.0..=1.0));
+        gun.kick += Vec2::new(recoil.x.to_radians(), recoil.y.to_radians());
+        fired_events.send(PlayerFired { position: origin });
 
         let ct = camera_transform;
         let mut projectile_trans = ct.compute_transform();
-        let look_at = origin + ct.forward() * 1000.0;
-        projectile_trans.translation = origin + ct.right() * 0.2 - ct.up() * 0.1;
+        let look_at = origin + direction * 1000.0;
+        projectile_trans.translation = ct.transform_point(weapon.muzzle_offset);
         projectile_trans.look_at(look_at, Vec3::Y);
         commands
             .spawn(SceneBundle {
-                scene: props.projectile_lite.clone(),
+                scene: weapon.projectile.scene(&props),
                 transform: projectile_trans,
                 ..default()
             })
             .insert(Projectile {
-                speed: 250.0,
+                speed: weapon.projectile_speed,
                 max_dist: 1000.0,
                 dist_trav: 0.0,
+                shooter: Some(logical_player_entity.0),
             })
             .insert(EnvSettings {
                 env_spec: 0.0,
@@ -215,7 +419,7 @@ fn player_shoot(
                 emit_mult: 10.0, //not working
             });
         // Then cast the ray.
-        let hit = rapier_context.cast_ray(
+        let hit = rapier_context.cast_ray_and_get_normal(
             origin,
             direction,
             f32::MAX,
@@ -226,60 +430,305 @@ fn player_shoot(
                 .exclude_sensors(),
         );
 
-        if let Some((hit_entity, toi)) = hit {
-            if commands.get_entity(hit_entity).is_some() {
-                if let Ok(mut health) = healths.get_mut(hit_entity) {
-                    let dmg_mult = 1.0 / (toi - 35.0).clamp(1.0, 100.0).powf(0.5);
-                    health.0 -= gun.attack_damage * dmg_mult;
+        if let Some((hit_entity, intersection)) = hit {
+            if commands.get_entity(hit_entity).is_some() && healths.contains(hit_entity) {
+                run_stats.shots_hit += 1;
+                damage_events.send(DamageEvent {
+                    target: hit_entity,
+                    source: Some(logical_player_entity.0),
+                    amount: weapon.damage * weapon.falloff(intersection.toi),
+                    kind: DamageKind::Bullet,
+                    point: intersection.point,
+                    normal: intersection.normal,
+                });
+            }
+        }
+    }
+}
+
+/// Starts reloading when asked to or when firing an empty magazine, and fills the magazine
+/// from the reserve once the reload time is up
+fn reload(
+    action_state: Res<ActionState>,
+    time: Res<Time>,
+    mut player: Query<&mut PlayerGun>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    for mut gun in &mut player {
+        let current = gun.current;
+        if gun.is_reloading() {
+            gun.reloading -= time.delta_seconds();
+            if !gun.is_reloading() {
+                if let Some(carried) = gun.weapons.get_mut(current) {
+                    let rounds = (carried.def.magazine - carried.magazine).min(carried.reserve);
+                    carried.magazine += rounds;
+                    carried.reserve -= rounds;
                 }
             }
+            continue;
+        }
+        let (empty, can_reload, reload_time) = match gun.carried() {
+            Some(carried) => (
+                carried.magazine == 0,
+                carried.can_reload(),
+                carried.def.reload_time,
+            ),
+            None => continue,
+        };
+        let wants_reload = action_state.just_pressed(Action::Reload)
+            || (action_state.pressed(Action::Fire) && empty);
+        if wants_reload && can_reload {
+            // Above zero, or it wouldn't count as reloading
+            gun.reloading = reload_time.max(0.01);
+            sfx.send(PlaySfx(Sfx::Reload));
         }
     }
 }
 
+/// Dips the gun out of view and back while reloading
+fn animate_reload(
+    player: Query<&PlayerGun>,
+    mut gun_models: Query<&mut Transform, With<GunModel>>,
+) {
+    for gun in &player {
+        let weapon = match gun.weapon() {
+            Some(weapon) => weapon,
+            None => continue,
+        };
+        let progress = if gun.is_reloading() && weapon.reload_time > 0.0 {
+            1.0 - gun.reloading / weapon.reload_time
+        } else {
+            0.0
+        };
+        let dip = (progress * PI).sin();
+        for mut trans in &mut gun_models {
+            trans.translation = weapon.viewmodel.offset - Vec3::Y * dip * 0.15;
+            trans.rotation = Quat::from_rotation_x(-dip * 0.6);
+        }
+    }
+}
+
+/// Recovers bloom over time and works out the spread of the next shot, including how fast
+/// the player is moving
+fn update_spread(
+    time: Res<Time>,
+    mut player: Query<(&mut PlayerGun, &LogicalPlayerEntity)>,
+    velocities: Query<&Velocity>,
+) {
+    for (mut gun, logical_player) in &mut player {
+        let weapon = match gun.weapon() {
+            Some(weapon) => weapon,
+            None => continue,
+        };
+        let recovery = weapon.bloom_recovery;
+        let speed = match velocities.get(logical_player.0) {
+            Ok(velocity) => (velocity.linvel * Vec3::new(1.0, 0.0, 1.0)).length(),
+            Err(_) => 0.0,
+        };
+        let bloom = (gun.bloom - recovery * time.delta_seconds()).max(0.0);
+        gun.spread = weapon.spread_at(bloom, speed);
+        gun.bloom = bloom;
+    }
+}
+
+/// Crosshair scale added per degree of spread, on top of its own size
+const CROSSHAIR_GROWTH: f32 = 0.15;
+
+/// Opens the crosshair up to show the current spread
+fn scale_crosshair(
+    player: Query<(&PlayerGun, &CrosshairRef)>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (gun, crosshair) in &player {
+        if let Ok(mut trans) = transforms.get_mut(crosshair.0) {
+            let scale = 1.0 + gun.spread * CROSSHAIR_GROWTH;
+            trans.scale = Vec3::new(scale, scale, 1.0);
+        }
+    }
+}
+
+/// Radians the view is knocked per unit of projectile damage
+const AIM_PUNCH: f32 = 0.4;
+
+/// Knocks the view up and away from the side projectiles hit the player on
+fn aim_punch(
+    mut damaged_events: EventReader<Damaged>,
+    mut player: Query<(&GlobalTransform, &mut PlayerGun)>,
+    mut rng: ResMut<GameRng>,
+) {
+    for Damaged(damage) in damaged_events.iter() {
+        if damage.kind != DamageKind::Projectile {
+            continue;
+        }
+        if let Ok((trans, mut gun)) = player.get_mut(damage.target) {
+            let side = trans.right().dot(damage.point - trans.translation());
+            let away = if side.abs() > 0.01 {
+                side.signum()
+            } else {
+                rng.gen_range(-1.0..=1.0)
+            };
+            gun.kick += Vec2::new(1.0, away * 0.5) * damage.amount * AIM_PUNCH;
+        }
+    }
+}
+
+/// How quickly kick is applied to the view, per second
+const KICK_SPEED: f32 = 25.0;
+
+/// Turns the view by the gun's kick over a few frames, so it doesn't snap
+fn apply_kick(
+    time: Res<Time>,
+    mut player: Query<(&mut PlayerGun, &LogicalPlayerEntity)>,
+    mut inputs: Query<&mut FpsControllerInput>,
+) {
+    for (mut gun, logical_player) in &mut player {
+        if gun.kick == Vec2::ZERO {
+            continue;
+        }
+        let step = if gun.kick.length() < 0.0001 {
+            gun.kick
+        } else {
+            gun.kick * (KICK_SPEED * time.delta_seconds()).min(1.0)
+        };
+        gun.kick -= step;
+        if let Ok(mut input) = inputs.get_mut(logical_player.0) {
+            input.pitch = (input.pitch + step.x).clamp(-FRAC_PI_2 + 0.001, FRAC_PI_2 - 0.001);
+            input.yaw += step.y;
+        }
+    }
+}
+
+/// A random direction within a cone of `spread` degrees around the camera's forward
+fn spread_direction(camera_transform: &GlobalTransform, spread: f32, rng: &mut GameRng) -> Vec3 {
+    let forward = camera_transform.forward();
+    if spread <= 0.0 {
+        return forward;
+    }
+    let max_angle = (spread * 0.5).to_radians();
+    // sqrt spreads shots evenly over the cone's area instead of bunching them in the middle
+    let angle = max_angle * rng.gen::<f32>().sqrt();
+    let around = rng.gen_range(0.0..TAU);
+    let sideways = camera_transform.right() * around.cos() + camera_transform.up() * around.sin();
+    (forward + sideways * angle.tan()).normalize()
+}
+
 #[derive(Component)]
 pub struct Projectile {
     pub speed: f32,
     pub max_dist: f32,
     pub dist_trav: f32,
+    /// Whoever fired it, its collider is ignored
+    pub shooter: Option<Entity>,
 }
 
-fn progress_projectiles(
+/// Projectiles are swept as balls this size, so they can't slip through gaps in colliders
+const PROJECTILE_RADIUS: f32 = 0.05;
+
+/// A projectile hit a collider and was despawned
+pub struct ProjectileHit {
+    pub projectile: Entity,
+    /// The entity of the collider that was hit
+    pub entity: Entity,
+    pub point: Vec3,
+    /// Of the surface that was hit, pointing out of it
+    pub normal: Vec3,
+    /// The projectile's shooter
+    pub source: Option<Entity>,
+    /// From the projectile's DamagePlayer
+    pub damage: Option<f32>,
+}
+
+/// Moves projectiles forward, sweeping them through the physics world so fast ones don't pass
+/// through thin walls
+pub fn progress_projectiles(
     mut commands: Commands,
     time: Res<Time>,
-    mut projectiles: Query<(Entity, &mut Transform, &mut Projectile)>,
+    rapier_context: Res<RapierContext>,
+    mut projectiles: Query<(
+        Entity,
+        &mut Transform,
+        &mut Projectile,
+        Option<&DamagePlayer>,
+    )>,
+    mut hit_events: EventWriter<ProjectileHit>,
 ) {
-    for (entity, mut trans, mut projectile) in &mut projectiles {
+    let shape = Collider::ball(PROJECTILE_RADIUS);
+    for (entity, mut trans, mut projectile, damage) in &mut projectiles {
         if projectile.dist_trav >= projectile.max_dist {
             commands.entity(entity).despawn_recursive();
+            continue;
         }
         let dist = time.delta_seconds() * projectile.speed;
-        trans.translation = trans.translation + trans.forward() * dist;
-        projectile.dist_trav += dist;
+        let dir = trans.forward();
+        let mut filter = QueryFilter::default().exclude_sensors();
+        if let Some(shooter) = projectile.shooter {
+            filter = filter.exclude_collider(shooter);
+        }
+        let hit =
+            rapier_context.cast_shape(trans.translation, Quat::IDENTITY, dir, &shape, dist, filter);
+        match hit {
+            Some((hit_entity, toi)) => {
+                let center = trans.translation + dir * toi.toi;
+                // The shape isn't rotated, so its local normal is also the world one. It's
+                // zero if the projectile started inside the collider
+                let normal = if toi.normal2 == Vec3::ZERO {
+                    -dir
+                } else {
+                    -toi.normal2
+                };
+                hit_events.send(ProjectileHit {
+                    projectile: entity,
+                    entity: hit_entity,
+                    point: center - normal * PROJECTILE_RADIUS,
+                    normal,
+                    source: projectile.shooter,
+                    damage: damage.map(|damage| damage.0),
+                });
+                commands.entity(entity).despawn_recursive();
+            }
+            None => {
+                trans.translation += dir * dist;
+                projectile.dist_trav += dist;
+            }
+        }
     }
 }
 
 fn respawn(
     mut commands: Commands,
-    mut query: Query<(&mut Transform, &mut Velocity, &mut FpsController)>,
-    mut health: Query<&mut Health, With<RenderPlayer>>,
+    mut query: Query<(Entity, &mut Transform, &mut Velocity, &mut FpsController)>,
+    mut health: Query<(Entity, &mut Health), With<RenderPlayer>>,
     state: Res<State<GameLevel>>,
+    levels: Levels,
     mut next_state: ResMut<NextState<GameLevel>>,
     units: Query<Entity, With<UnitData>>,
-    levels_started: Res<LevelsStarted>,
+    app_state: Res<State<AppState>>,
+    mut trigger_entered: EventReader<TriggerEntered>,
+    mut died_events: EventReader<Died>,
+    mut run_stats: ResMut<RunStats>,
 ) {
-    if !levels_started.0 {
+    if app_state.0 != AppState::InGame {
         return;
     }
-    if let Some(mut health) = health.iter_mut().next() {
-        if let Some((mut transform, mut velocity, mut fps_controller)) = query.iter_mut().next() {
-            if transform.translation.y < -500.0 || health.0 <= 0.0 {
+    let entered_kill_zone = trigger_entered
+        .iter()
+        .any(|entered| entered.kind == TriggerKind::KillZone);
+    if let Some((render_player, mut health)) = health.iter_mut().next() {
+        let died = died_events.iter().any(|died| died.entity == render_player);
+        if let Some((entity, mut transform, mut velocity, mut fps_controller)) =
+            query.iter_mut().next()
+        {
+            if entered_kill_zone || transform.translation.y < -500.0 || died {
+                run_stats.deaths += 1;
                 fps_controller.gravity = 0.0;
                 health.0 = 1.0;
                 next_state.set(state.0.clone());
 
                 velocity.linvel = Vec3::ZERO;
-                transform.translation = state.0.spawn_pos();
+                transform.translation = levels.get(&state.0).spawn_pos;
+                commands
+                    .entity(entity)
+                    .insert(AwaitingSpawnPoint(state.0.clone()));
                 for unit in &units {
                     if commands.get_entity(unit).is_some() {
                         commands.entity(unit).despawn_recursive();
@@ -290,15 +739,113 @@ fn respawn(
     }
 }
 
+/// The player is at the fallback spawn_pos from the level manifest until the level's
+/// PlayerSpawn node has been spawned
+#[derive(Component)]
+pub struct AwaitingSpawnPoint(pub GameLevel);
+
+fn place_at_spawn_point(
+    mut commands: Commands,
+    mut player: Query<(
+        Entity,
+        &AwaitingSpawnPoint,
+        &mut Transform,
+        &mut Velocity,
+        &mut FpsControllerInput,
+    )>,
+    spawn_points: Query<(&GlobalTransform, &PlayerSpawn)>,
+) {
+    for (entity, awaiting, mut transform, mut velocity, mut input) in &mut player {
+        for (spawn_trans, spawn) in &spawn_points {
+            if spawn.0 != awaiting.0 {
+                continue;
+            }
+            let spawn_trans = spawn_trans.compute_transform();
+            let forward = spawn_trans.forward();
+            transform.translation = spawn_trans.translation;
+            velocity.linvel = Vec3::ZERO;
+            input.yaw = (-forward.x).atan2(-forward.z);
+            input.pitch = 0.0;
+            commands.entity(entity).remove::<AwaitingSpawnPoint>();
+            break;
+        }
+    }
+}
+
 fn gun_visibility(
     mut gun_models: Query<&mut Visibility, With<GunModel>>,
     level: Res<State<GameLevel>>,
+    levels: Levels,
 ) {
     for mut gun_vis in &mut gun_models {
-        if level.0.show_gun() {
+        if levels.get(&level.0).show_gun {
             *gun_vis = Visibility::Visible
         } else {
             *gun_vis = Visibility::Hidden
         }
     }
 }
+
+pub fn teleport_player(
+    mut commands: Commands,
+    mut teleports: EventReader<TeleportPlayer>,
+    mut next_level: ResMut<NextState<GameLevel>>,
+    mut player: Query<(Entity, &mut Transform, &mut Velocity, &mut FpsController)>,
+    mut health: Query<&mut Health, With<RenderPlayer>>,
+    units: Query<Entity, With<UnitData>>,
+    levels: Levels,
+) {
+    if let Some(TeleportPlayer(level)) = teleports.iter().last() {
+        if let Some((player_entity, mut transform, mut velocity, mut fps_controller)) =
+            player.iter_mut().next()
+        {
+            teleport(
+                levels.get(level),
+                player_entity,
+                &mut fps_controller,
+                &mut health,
+                level.clone(),
+                &mut next_level,
+                &mut velocity,
+                &mut transform,
+                &units,
+                &mut commands,
+            );
+        }
+    }
+}
+
+fn teleport(
+    level_props: &LevelProperties,
+    player_entity: Entity,
+    fps_controller: &mut FpsController,
+    health: &mut Query<&mut Health, With<RenderPlayer>>,
+    level: GameLevel,
+    next_level: &mut NextState<GameLevel>,
+    velocity: &mut Velocity,
+    transform: &mut Transform,
+    units: &Query<Entity, With<UnitData>>,
+    commands: &mut Commands,
+) {
+    fps_controller.gravity = 0.0;
+    if let Some(mut health) = health.iter_mut().next() {
+        health.0 = 1.0;
+    }
+    if level_props.player_can_jump {
+        fps_controller.jump_speed = JUMP_SPEED;
+    } else {
+        fps_controller.jump_speed = 0.0;
+    }
+    next_level.set(level.clone());
+
+    velocity.linvel = Vec3::ZERO;
+    transform.translation = level_props.spawn_pos;
+    commands
+        .entity(player_entity)
+        .insert(AwaitingSpawnPoint(level));
+    for unit in units {
+        if commands.get_entity(unit).is_some() {
+            commands.entity(unit).despawn_recursive();
+        }
+    }
+}
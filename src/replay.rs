@@ -0,0 +1,397 @@
+//! Records the inputs of each run started from the kitchen and plays them back through the same
+//! systems. Gameplay that depends on frame time is fed the recorded frame times, and units run
+//! on the fixed timestep, so with the fixed rng seed a run plays out the same way again.
+//! The last run is kept in the data dir as replay.json, for bug reports.
+
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    time::{TimeSystem, TimeUpdateStrategy},
+    utils::Instant,
+};
+use bevy_fps_controller::controller::{
+    fps_controller_input, fps_controller_render, FpsController, FpsControllerInput,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    character_controller::manage_cursor,
+    gamepad::gamepad_to_controller,
+    input::{actions_to_controller, update_action_state, ActionState},
+    menu::{new_game, NewGame},
+    pause::{update_pause, SettingClock},
+    settings::{invert_mouse_y, DifficultyOverride, Settings},
+    storage,
+    ui::{run_menu_commands, ui_system, MenuCommand, TextFeed},
+    units::Difficulty,
+    AppState, GameLoading, TIMESTEP,
+};
+
+const REPLAY_KEY: &str = "replay";
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Replay::load())
+            .add_event::<PlayReplay>()
+            .add_system(
+                playback_time
+                    .in_base_set(CoreSet::First)
+                    .before(TimeSystem)
+                    .run_if(replay_playing),
+            )
+            .add_system(
+                playback_actions
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(update_action_state)
+                    .run_if(replay_playing),
+            )
+            .add_systems(
+                (play_replay.before(new_game), start_run.after(new_game))
+                    .distributive_run_if(in_state(GameLoading::Loaded)),
+            )
+            .add_systems(
+                (
+                    playback_controller
+                        .after(fps_controller_input)
+                        .after(actions_to_controller)
+                        .after(gamepad_to_controller)
+                        .after(invert_mouse_y)
+                        .before(fps_controller_render),
+                    playback_menu_commands
+                        .after(ui_system)
+                        .before(run_menu_commands),
+                    playback_pause.after(manage_cursor).before(update_pause),
+                )
+                    .distributive_run_if(replay_playing),
+            )
+            .add_systems(
+                (
+                    record_frame.run_if(replay_recording),
+                    advance_playback.run_if(replay_playing),
+                )
+                    .in_base_set(CoreSet::Last),
+            )
+            .add_system(end_run.in_schedule(OnEnter(AppState::Results)))
+            .add_system(end_run.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_system(autoplay.in_schedule(OnEnter(GameLoading::Loaded)));
+    }
+}
+
+/// Play back the last recorded run from the start
+pub struct PlayReplay;
+
+#[derive(Default, PartialEq, Eq, Clone, Copy, Debug)]
+enum ReplayMode {
+    #[default]
+    Off,
+    Recording,
+    Playback,
+}
+
+/// Controller input after keyboard, mouse and gamepad have all been applied
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+struct ControllerFrame {
+    pitch: f32,
+    yaw: f32,
+    movement: Vec3,
+    jump: bool,
+    sprint: bool,
+    crouch: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayFrame {
+    delta: Duration,
+    actions: ActionState,
+    controller: ControllerFrame,
+    /// Whether the cursor was locked, the game pauses when it isn't
+    enable_input: bool,
+    setting_clock: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    menu_commands: Vec<MenuCommand>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Recording {
+    /// Fixed timestep the run was recorded with
+    timestep: f32,
+    difficulty: Difficulty,
+    frames: Vec<ReplayFrame>,
+}
+
+impl Recording {
+    fn from_json(contents: &str) -> Option<Recording> {
+        match serde_json::from_str(contents) {
+            Ok(recording) => Some(recording),
+            Err(e) => {
+                warn!("Ignoring unreadable replay: {e}");
+                None
+            }
+        }
+    }
+
+    fn write(&self) {
+        match serde_json::to_string(self) {
+            Ok(contents) => storage::write(REPLAY_KEY, &contents),
+            Err(e) => warn!("Failed to serialize replay: {e}"),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Replay {
+    mode: ReplayMode,
+    recording: Recording,
+    /// Next frame to play back
+    frame: usize,
+    /// Instant the last played back frame ended at
+    clock: Option<Instant>,
+    /// Start playing as soon as loading is done, for replays passed on the command line
+    autoplay: bool,
+}
+
+impl Replay {
+    /// The last run, or a replay file passed with `--replay <path>`
+    fn load() -> Replay {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let args: Vec<String> = std::env::args().collect();
+            if let Some(path) = args
+                .iter()
+                .position(|arg| arg == "--replay")
+                .and_then(|i| args.get(i + 1))
+            {
+                match std::fs::read_to_string(path) {
+                    Ok(contents) => {
+                        if let Some(recording) = Recording::from_json(&contents) {
+                            return Replay {
+                                recording,
+                                autoplay: true,
+                                ..default()
+                            };
+                        }
+                    }
+                    Err(e) => warn!("Failed to read replay {path}: {e}"),
+                }
+            }
+        }
+        Replay {
+            recording: storage::read(REPLAY_KEY)
+                .and_then(|contents| Recording::from_json(&contents))
+                .unwrap_or_default(),
+            ..default()
+        }
+    }
+
+    pub fn has_recording(&self) -> bool {
+        !self.recording.frames.is_empty()
+    }
+
+    fn current_frame(&self) -> Option<&ReplayFrame> {
+        self.recording.frames.get(self.frame)
+    }
+}
+
+fn replay_playing(replay: Res<Replay>) -> bool {
+    replay.mode == ReplayMode::Playback
+}
+
+fn replay_recording(replay: Res<Replay>) -> bool {
+    replay.mode == ReplayMode::Recording
+}
+
+/// Saves, splits and records shouldn't change while watching a replay
+pub fn not_replaying(replay: Res<Replay>) -> bool {
+    replay.mode != ReplayMode::Playback
+}
+
+fn autoplay(replay: Res<Replay>, mut play_replay: EventWriter<PlayReplay>) {
+    if replay.autoplay {
+        play_replay.send(PlayReplay);
+    }
+}
+
+fn play_replay(
+    mut events: EventReader<PlayReplay>,
+    mut replay: ResMut<Replay>,
+    mut difficulty_override: ResMut<DifficultyOverride>,
+    mut new_game: EventWriter<NewGame>,
+    time: Res<Time>,
+) {
+    if events.iter().last().is_none() || !replay.has_recording() {
+        return;
+    }
+    replay.mode = ReplayMode::Playback;
+    replay.frame = 0;
+    replay.clock = time.last_update();
+    difficulty_override.0 = Some(replay.recording.difficulty);
+    new_game.send(NewGame);
+}
+
+/// Every run starts recording, unless it was started by a replay
+fn start_run(
+    mut events: EventReader<NewGame>,
+    mut replay: ResMut<Replay>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut difficulty: ResMut<Difficulty>,
+    mut difficulty_override: ResMut<DifficultyOverride>,
+    settings: Res<Settings>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+    if replay.mode != ReplayMode::Playback {
+        // A new run is on the difficulty in settings, even after continuing another
+        if difficulty_override.0.take().is_some() {
+            *difficulty = settings.difficulty;
+        }
+        replay.mode = ReplayMode::Recording;
+        replay.recording = Recording {
+            timestep: TIMESTEP,
+            difficulty: *difficulty,
+            frames: Vec::new(),
+        };
+    }
+    // The fixed timestep starts with nothing accumulated either way
+    *fixed_time = FixedTime::new_from_secs(replay.recording.timestep);
+}
+
+fn end_run(
+    mut replay: ResMut<Replay>,
+    mut time_update: ResMut<TimeUpdateStrategy>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut difficulty_override: ResMut<DifficultyOverride>,
+    app_state: Res<State<AppState>>,
+) {
+    match replay.mode {
+        ReplayMode::Recording => {
+            replay.recording.write();
+            replay.mode = ReplayMode::Off;
+        }
+        // Keep watching the results screen until the recording runs out
+        ReplayMode::Playback if app_state.0 == AppState::MainMenu => {
+            stop_playback(
+                &mut replay,
+                &mut time_update,
+                &mut fixed_time,
+                &mut difficulty_override,
+            );
+        }
+        _ => (),
+    }
+}
+
+fn stop_playback(
+    replay: &mut Replay,
+    time_update: &mut TimeUpdateStrategy,
+    fixed_time: &mut FixedTime,
+    difficulty_override: &mut DifficultyOverride,
+) {
+    replay.mode = ReplayMode::Off;
+    replay.clock = None;
+    *time_update = TimeUpdateStrategy::Automatic;
+    *fixed_time = FixedTime::new_from_secs(TIMESTEP);
+    difficulty_override.0 = None;
+}
+
+fn record_frame(
+    mut replay: ResMut<Replay>,
+    time: Res<Time>,
+    action_state: Res<ActionState>,
+    player: Query<(&FpsController, &FpsControllerInput)>,
+    setting_clock: Res<SettingClock>,
+    mut menu_commands: EventReader<MenuCommand>,
+) {
+    if let Some((fps_controller, input)) = player.iter().next() {
+        let frame = ReplayFrame {
+            delta: time.delta(),
+            actions: action_state.clone(),
+            controller: ControllerFrame {
+                pitch: input.pitch,
+                yaw: input.yaw,
+                movement: input.movement,
+                jump: input.jump,
+                sprint: input.sprint,
+                crouch: input.crouch,
+            },
+            enable_input: fps_controller.enable_input,
+            setting_clock: setting_clock.0,
+            menu_commands: menu_commands.iter().cloned().collect(),
+        };
+        replay.recording.frames.push(frame);
+    }
+}
+
+/// Step time by the recorded frame time instead of the real one
+fn playback_time(mut replay: ResMut<Replay>, mut time_update: ResMut<TimeUpdateStrategy>) {
+    if let Some(delta) = replay.current_frame().map(|frame| frame.delta) {
+        let clock = replay.clock.unwrap_or_else(Instant::now) + delta;
+        replay.clock = Some(clock);
+        *time_update = TimeUpdateStrategy::ManualInstant(clock);
+    }
+}
+
+fn playback_actions(replay: Res<Replay>, mut action_state: ResMut<ActionState>) {
+    if let Some(frame) = replay.current_frame() {
+        *action_state = frame.actions.clone();
+    }
+}
+
+fn playback_controller(replay: Res<Replay>, mut player: Query<&mut FpsControllerInput>) {
+    if let Some(frame) = replay.current_frame() {
+        for mut input in &mut player {
+            let controller = frame.controller;
+            input.pitch = controller.pitch;
+            input.yaw = controller.yaw;
+            input.movement = controller.movement;
+            input.jump = controller.jump;
+            input.sprint = controller.sprint;
+            input.crouch = controller.crouch;
+            input.fly = false;
+        }
+    }
+}
+
+/// Only the recorded menu commands are run, not ones from clicking around during playback
+fn playback_menu_commands(replay: Res<Replay>, mut menu_commands: ResMut<Events<MenuCommand>>) {
+    menu_commands.clear();
+    if let Some(frame) = replay.current_frame() {
+        for command in &frame.menu_commands {
+            menu_commands.send(command.clone());
+        }
+    }
+}
+
+fn playback_pause(
+    replay: Res<Replay>,
+    mut fps_controller: Query<&mut FpsController>,
+    mut setting_clock: ResMut<SettingClock>,
+) {
+    if let Some(frame) = replay.current_frame() {
+        for mut fps_controller in &mut fps_controller {
+            fps_controller.enable_input = frame.enable_input;
+        }
+        setting_clock.0 = frame.setting_clock;
+    }
+}
+
+fn advance_playback(
+    mut replay: ResMut<Replay>,
+    mut time_update: ResMut<TimeUpdateStrategy>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut difficulty_override: ResMut<DifficultyOverride>,
+    mut text_feed: ResMut<TextFeed>,
+) {
+    replay.frame += 1;
+    if replay.current_frame().is_none() {
+        stop_playback(
+            &mut replay,
+            &mut time_update,
+            &mut fixed_time,
+            &mut difficulty_override,
+        );
+        text_feed.push("Replay finished.");
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt::Write;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    levels::GameLevel, pause::PauseState, replay::not_replaying, storage, AppState, GameLoading,
+};
+
+const RECORDS_KEY: &str = "records";
+
+pub struct RunTimerPlugin;
+impl Plugin for RunTimerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTimer>()
+            .insert_resource(SplitRecords::load())
+            .add_system(
+                record_splits
+                    .run_if(in_state(GameLoading::Loaded))
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(not_replaying),
+            )
+            .add_system(hold_run_timer.run_if(in_state(PauseState::Paused)))
+            .add_system(
+                record_personal_best
+                    .run_if(not_replaying)
+                    .in_schedule(OnEnter(AppState::Results)),
+            );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Split {
+    pub level: GameLevel,
+    /// Run time when the level was entered
+    pub entered: f32,
+}
+
+/// Time for the current run. Started in the kitchen, finished when returning there from the
+/// control room
+#[derive(Resource, Default)]
+pub struct RunTimer {
+    /// Time::elapsed_seconds() the run started at, moved forward while paused
+    start: Option<f32>,
+    /// Run time the game was finished at
+    finished: Option<f32>,
+    pub splits: Vec<Split>,
+}
+
+impl RunTimer {
+    pub fn start(&mut self, now: f32) {
+        self.start = Some(now);
+    }
+
+    /// Continue a run that already had `elapsed` seconds on the clock
+    pub fn resume(&mut self, now: f32, elapsed: f32, splits: Vec<Split>) {
+        self.start = Some(now - elapsed);
+        self.finished = None;
+        self.splits = splits;
+    }
+
+    pub fn finish(&mut self, now: f32) {
+        self.finished = self.elapsed(now);
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.start.is_some()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// Don't count time that passes while paused
+    pub fn hold(&mut self, delta: f32) {
+        if let (Some(start), None) = (&mut self.start, self.finished) {
+            *start += delta;
+        }
+    }
+
+    pub fn elapsed(&self, now: f32) -> Option<f32> {
+        self.finished.or(self.start.map(|start| now - start))
+    }
+
+    /// Time spent in each level, the last one runs until `end`
+    pub fn segments(&self, end: f32) -> Vec<(GameLevel, f32)> {
+        segments(&self.splits, end)
+    }
+
+    /// Ahead (negative) or behind (positive) the personal best. Compares the time this level
+    /// was entered to when the PB entered it, until the PB would have left the level
+    pub fn delta(&self, pb: &PersonalBest, now: f32) -> Option<f32> {
+        let elapsed = self.elapsed(now)?;
+        let current = self.splits.last()?;
+        let pb_index = pb.splits.iter().position(|s| s.level == current.level)?;
+        let pb_entered = pb.splits[pb_index].entered;
+        let pb_left = pb
+            .splits
+            .get(pb_index + 1)
+            .map(|s| s.entered)
+            .unwrap_or(pb.total);
+        if elapsed > pb_left {
+            Some(elapsed - pb_left)
+        } else {
+            Some(current.entered - pb_entered)
+        }
+    }
+}
+
+fn segments(splits: &[Split], end: f32) -> Vec<(GameLevel, f32)> {
+    splits
+        .iter()
+        .enumerate()
+        .map(|(i, split)| {
+            let next = splits.get(i + 1).map(|s| s.entered).unwrap_or(end);
+            (split.level.clone(), next - split.entered)
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PersonalBest {
+    pub total: f32,
+    pub splits: Vec<Split>,
+}
+
+/// Personal best and best time for each level (gold splits), saved across runs
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct SplitRecords {
+    pub personal_best: Option<PersonalBest>,
+    pub golds: HashMap<GameLevel, f32>,
+}
+
+impl SplitRecords {
+    pub fn load() -> SplitRecords {
+        if let Some(contents) = storage::read(RECORDS_KEY) {
+            match serde_json::from_str(&contents) {
+                Ok(records) => return records,
+                Err(e) => warn!("Ignoring unreadable split records: {e}"),
+            }
+        }
+        SplitRecords::default()
+    }
+
+    pub fn write(&self) {
+        match serde_json::to_string_pretty(self) {
+            Ok(contents) => storage::write(RECORDS_KEY, &contents),
+            Err(e) => warn!("Failed to serialize split records: {e}"),
+        }
+    }
+
+    /// Returns true if this was a new gold
+    fn update_gold(&mut self, level: &GameLevel, time: f32) -> bool {
+        match self.golds.get(level) {
+            Some(gold) if *gold <= time => false,
+            _ => {
+                self.golds.insert(level.clone(), time);
+                true
+            }
+        }
+    }
+
+    /// The personal best in LiveSplit's .lss format
+    pub fn to_livesplit(&self) -> Option<String> {
+        let pb = self.personal_best.as_ref()?;
+        let mut lss = String::new();
+        lss.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        lss.push_str("<Run version=\"1.7.0\">\n");
+        lss.push_str("  <GameIcon />\n  <GameName>Traverse</GameName>\n");
+        lss.push_str("  <CategoryName>Any%</CategoryName>\n");
+        lss.push_str("  <Offset>00:00:00</Offset>\n  <AttemptCount>0</AttemptCount>\n");
+        lss.push_str("  <AttemptHistory />\n  <Segments>\n");
+        let mut split_time = 0.0;
+        for (level, segment) in segments(&pb.splits, pb.total) {
+            split_time += segment;
+            let gold = self.golds.get(&level).copied().unwrap_or(segment);
+            let _ = write!(
+                lss,
+                "    <Segment>\n      <Name>{:?}</Name>\n      <Icon />\n      <SplitTimes>\n        \
+                 <SplitTime name=\"Personal Best\">\n          <RealTime>{}</RealTime>\n        \
+                 </SplitTime>\n      </SplitTimes>\n      <BestSegmentTime>\n        \
+                 <RealTime>{}</RealTime>\n      </BestSegmentTime>\n      <SegmentHistory />\n    \
+                 </Segment>\n",
+                level,
+                livesplit_time(split_time),
+                livesplit_time(gold),
+            );
+        }
+        lss.push_str("  </Segments>\n  <AutoSplitterSettings />\n</Run>\n");
+        Some(lss)
+    }
+}
+
+/// hh:mm:ss.fffffff
+fn livesplit_time(seconds: f32) -> String {
+    let total = seconds.max(0.0) as f64;
+    let hours = (total / 3600.0).floor();
+    let minutes = ((total - hours * 3600.0) / 60.0).floor();
+    let secs = total - hours * 3600.0 - minutes * 60.0;
+    format!("{:02}:{:02}:{:010.7}", hours, minutes, secs)
+}
+
+fn hold_run_timer(mut run_timer: ResMut<RunTimer>, time: Res<Time>) {
+    run_timer.hold(time.delta_seconds());
+}
+
+fn record_splits(
+    level: Res<State<GameLevel>>,
+    mut run_timer: ResMut<RunTimer>,
+    mut records: ResMut<SplitRecords>,
+    time: Res<Time>,
+) {
+    if !level.is_changed() || run_timer.is_finished() {
+        return;
+    }
+    // Restarting a level keeps counting towards the same split
+    if run_timer.splits.last().map(|split| &split.level) == Some(&level.0) {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    let entered = run_timer.elapsed(now).unwrap_or_default();
+    if let Some(previous) = run_timer.splits.last() {
+        let segment = entered - previous.entered;
+        if records.update_gold(&previous.level, segment) {
+            records.write();
+        }
+    }
+    run_timer.splits.push(Split {
+        level: level.0.clone(),
+        entered,
+    });
+}
+
+pub fn record_personal_best(
+    run_timer: Res<RunTimer>,
+    mut records: ResMut<SplitRecords>,
+    time: Res<Time>,
+) {
+    if let (Some(total), Some(last)) = (
+        run_timer.elapsed(time.elapsed_seconds()),
+        run_timer.splits.last(),
+    ) {
+        records.update_gold(&last.level, total - last.entered);
+        let is_pb = records
+            .personal_best
+            .as_ref()
+            .map_or(true, |pb| total < pb.total);
+        if is_pb {
+            records.personal_best = Some(PersonalBest {
+                total,
+                splits: run_timer.splits.clone(),
+            });
+        }
+        records.write();
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    levels::GameLevel,
+    player::{teleport_player, TeleportPlayer},
+    replay::not_replaying,
+    run_timer::{RunTimer, Split},
+    settings::DifficultyOverride,
+    storage,
+    ui::HasEnteredControlRoom,
+    units::Difficulty,
+    AppState, GameLoading,
+};
+
+const SAVE_KEY: &str = "save";
+
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ContinueGame>()
+            .init_resource::<DiscoveredCodes>()
+            .insert_resource(SavedGame(SaveGame::load()))
+            .add_systems(
+                (
+                    continue_game.before(teleport_player),
+                    autosave.run_if(not_replaying),
+                )
+                    .distributive_run_if(in_state(GameLoading::Loaded)),
+            )
+            .add_system(clear_save.in_schedule(OnEnter(AppState::Results)))
+            .add_system(end_continued_run.in_schedule(OnEnter(AppState::MainMenu)));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveGame {
+    pub level: GameLevel,
+    /// Seconds since the run started in the kitchen
+    pub elapsed: f32,
+    pub difficulty: Difficulty,
+    pub has_entered_control_room: bool,
+    pub discovered_codes: Vec<String>,
+    #[serde(default)]
+    pub splits: Vec<Split>,
+}
+
+impl SaveGame {
+    pub fn load() -> Option<SaveGame> {
+        let contents = storage::read(SAVE_KEY)?;
+        match serde_json::from_str(&contents) {
+            Ok(save) => Some(save),
+            Err(e) => {
+                warn!("Ignoring unreadable save: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn write(&self) {
+        match serde_json::to_string_pretty(self) {
+            Ok(contents) => storage::write(SAVE_KEY, &contents),
+            Err(e) => warn!("Failed to serialize save: {e}"),
+        }
+    }
+
+    pub fn remove() {
+        storage::remove(SAVE_KEY);
+    }
+}
+
+/// The latest save, offered as "Continue"
+#[derive(Resource, Default)]
+pub struct SavedGame(pub Option<SaveGame>);
+
+/// Teleporter codes the player has entered into a clock
+#[derive(Resource, Default)]
+pub struct DiscoveredCodes(pub Vec<String>);
+
+impl DiscoveredCodes {
+    pub fn add(&mut self, code: &str) {
+        if !self.0.iter().any(|c| c == code) {
+            self.0.push(code.to_string());
+        }
+    }
+}
+
+/// Resume from the save in SavedGame
+pub struct ContinueGame;
+
+fn continue_game(
+    mut events: EventReader<ContinueGame>,
+    saved: Res<SavedGame>,
+    mut teleports: EventWriter<TeleportPlayer>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut run_timer: ResMut<RunTimer>,
+    mut has_entered_control_room: ResMut<HasEnteredControlRoom>,
+    mut difficulty_override: ResMut<DifficultyOverride>,
+    mut discovered_codes: ResMut<DiscoveredCodes>,
+    time: Res<Time>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+    if let Some(save) = &saved.0 {
+        next_app_state.set(AppState::InGame);
+        run_timer.resume(time.elapsed_seconds(), save.elapsed, save.splits.clone());
+        has_entered_control_room.0 = save.has_entered_control_room;
+        // Keep playing the run on the difficulty it was started with, without overwriting the
+        // player's choice in settings
+        difficulty_override.0 = Some(save.difficulty);
+        discovered_codes.0 = save.discovered_codes.clone();
+        teleports.send(TeleportPlayer(save.level.clone()));
+    }
+}
+
+/// Write a save every time a level is entered
+fn autosave(
+    mut saved: ResMut<SavedGame>,
+    level: Res<State<GameLevel>>,
+    app_state: Res<State<AppState>>,
+    run_timer: Res<RunTimer>,
+    has_entered_control_room: Res<HasEnteredControlRoom>,
+    difficulty: Res<Difficulty>,
+    discovered_codes: Res<DiscoveredCodes>,
+    time: Res<Time>,
+) {
+    if !level.is_changed() || app_state.0 != AppState::InGame {
+        return;
+    }
+    let elapsed = run_timer
+        .elapsed(time.elapsed_seconds())
+        .unwrap_or_default();
+    let save = SaveGame {
+        level: level.0.clone(),
+        elapsed,
+        difficulty: *difficulty,
+        has_entered_control_room: has_entered_control_room.0,
+        discovered_codes: discovered_codes.0.clone(),
+        splits: run_timer.splits.clone(),
+    };
+    save.write();
+    saved.0 = Some(save);
+}
+
+/// A finished run can't be continued
+fn clear_save(mut saved: ResMut<SavedGame>) {
+    saved.0 = None;
+    SaveGame::remove();
+}
+
+/// The main menu shows and starts runs on the difficulty in settings
+fn end_continued_run(mut difficulty_override: ResMut<DifficultyOverride>) {
+    difficulty_override.0 = None;
+}
@@ -0,0 +1,179 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{input::mouse::MouseMotion, prelude::*};
+use bevy_fps_controller::controller::{
+    fps_controller_input, fps_controller_render, FpsController, FpsControllerInput, RenderPlayer,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{gamepad::StickSettings, input::InputBindings, storage, units::Difficulty};
+
+const SETTINGS_KEY: &str = "settings";
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::load())
+            .init_resource::<AudioVolumes>()
+            .init_resource::<DifficultyOverride>()
+            .add_systems((apply_settings, save_settings))
+            .add_system(
+                invert_mouse_y
+                    .after(fps_controller_input)
+                    .before(fps_controller_render),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct AudioVolumes {
+    pub sfx: f32,
+    pub music: f32,
+}
+
+impl Default for AudioVolumes {
+    fn default() -> Self {
+        AudioVolumes {
+            sfx: 0.5,
+            music: 0.5,
+        }
+    }
+}
+
+/// The difficulty of the current run when it isn't the one in settings, like a continued run or a
+/// replay. Kept out of Settings so it isn't saved
+#[derive(Resource, Default)]
+pub struct DifficultyOverride(pub Option<Difficulty>);
+
+/// Movement keys, read by the fps controller directly. Other inputs go through actions
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub forward: KeyCode,
+    pub back: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            forward: KeyCode::W,
+            back: KeyCode::S,
+            left: KeyCode::A,
+            right: KeyCode::D,
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// Radians per pixel of mouse motion
+    pub sensitivity: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub difficulty: Difficulty,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    pub invert_y: bool,
+    /// Show the personal best run's ghost
+    pub show_ghost: bool,
+    pub key_bindings: KeyBindings,
+    pub bindings: InputBindings,
+    pub stick: StickSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let audio_volumes = AudioVolumes::default();
+        Settings {
+            sensitivity: 0.001,
+            sfx_volume: audio_volumes.sfx,
+            music_volume: audio_volumes.music,
+            difficulty: Difficulty::default(),
+            fov: 72.0,
+            invert_y: false,
+            show_ghost: true,
+            key_bindings: KeyBindings::default(),
+            bindings: InputBindings::default(),
+            stick: StickSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        if let Some(contents) = storage::read(SETTINGS_KEY) {
+            match serde_json::from_str(&contents) {
+                Ok(settings) => return settings,
+                Err(e) => warn!("Ignoring unreadable settings: {e}"),
+            }
+        }
+        Settings::default()
+    }
+
+    pub fn apply_to_controller(&self, fps_controller: &mut FpsController) {
+        let keys = &self.key_bindings;
+        fps_controller.sensitivity = self.sensitivity;
+        fps_controller.key_forward = keys.forward;
+        fps_controller.key_back = keys.back;
+        fps_controller.key_left = keys.left;
+        fps_controller.key_right = keys.right;
+    }
+
+    pub fn write(&self) {
+        match serde_json::to_string_pretty(self) {
+            Ok(contents) => storage::write(SETTINGS_KEY, &contents),
+            Err(e) => warn!("Failed to serialize settings: {e}"),
+        }
+    }
+}
+
+fn apply_settings(
+    settings: Res<Settings>,
+    mut audio_volumes: ResMut<AudioVolumes>,
+    mut difficulty: ResMut<Difficulty>,
+    difficulty_override: Res<DifficultyOverride>,
+    mut fps_controllers: Query<&mut FpsController>,
+    mut cameras: Query<&mut Projection, With<RenderPlayer>>,
+) {
+    if !settings.is_changed() && !difficulty_override.is_changed() {
+        return;
+    }
+    audio_volumes.sfx = settings.sfx_volume;
+    audio_volumes.music = settings.music_volume;
+    *difficulty = difficulty_override.0.unwrap_or(settings.difficulty);
+    for mut fps_controller in &mut fps_controllers {
+        settings.apply_to_controller(&mut fps_controller);
+    }
+    for mut projection in &mut cameras {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = settings.fov.to_radians();
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.write();
+    }
+}
+
+/// The fps controller has no option for this, so undo its pitch change and apply it the other way
+pub fn invert_mouse_y(
+    settings: Res<Settings>,
+    mut mouse_events: EventReader<MouseMotion>,
+    mut query: Query<(&FpsController, &mut FpsControllerInput)>,
+) {
+    let delta_y: f32 = mouse_events.iter().map(|event| event.delta.y).sum();
+    if !settings.invert_y {
+        return;
+    }
+    for (controller, mut input) in &mut query {
+        if controller.enable_input {
+            input.pitch = (input.pitch + 2.0 * delta_y * controller.sensitivity)
+                .clamp(-FRAC_PI_2 + 0.001, FRAC_PI_2 - 0.001);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+/// Sound sets, the client picks a variation of each
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Sfx {
+    PlayerGun,
+    EnemyGun,
+    PlayerHit,
+    EnemyExplode,
+    Reload,
+    Pickup,
+}
+
+/// Sent by gameplay systems and played by the client's GameAudioPlugin, so gameplay doesn't
+/// need audio to run
+pub struct PlaySfx(pub Sfx);
@@ -0,0 +1,97 @@
+//! Small key/value store for save data. Files in the user's data dir on desktop, localStorage on the web.
+
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir() -> Option<std::path::PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "dgriffin", "traverse")?;
+    Some(dirs.data_dir().to_path_buf())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path_for(key: &str) -> Option<std::path::PathBuf> {
+    Some(data_dir()?.join(format!("{key}.json")))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(path_for(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(key: &str, contents: &str) {
+    match path_for(key) {
+        Some(path) => {
+            write_file(&path, contents);
+        }
+        None => bevy::log::warn!("No data directory to write {key} to"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(key: &str) {
+    if let Some(path) = path_for(key) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                bevy::log::warn!("Failed to remove {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Write a file for use outside the game (like LiveSplit splits) to the data directory.
+/// Returns where it was written
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export(file_name: &str, contents: &str) -> Option<std::path::PathBuf> {
+    let path = data_dir()?.join(file_name);
+    write_file(&path, contents).then_some(path)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_file(path: &std::path::Path, contents: &str) -> bool {
+    if let Some(dir) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            bevy::log::warn!("Failed to create {}: {e}", dir.display());
+            return false;
+        }
+    }
+    if let Err(e) = std::fs::write(path, contents) {
+        bevy::log::warn!("Failed to write {}: {e}", path.display());
+        return false;
+    }
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(key: &str) -> Option<String> {
+    local_storage()?.get_item(&format!("traverse_{key}")).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(key: &str, contents: &str) {
+    let storage = match local_storage() {
+        Some(storage) => storage,
+        None => {
+            bevy::log::warn!("localStorage unavailable, can't write {key}");
+            return;
+        }
+    };
+    if storage
+        .set_item(&format!("traverse_{key}"), contents)
+        .is_err()
+    {
+        bevy::log::warn!("Failed to write {key} to localStorage");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(key: &str) {
+    if let Some(storage) = local_storage() {
+        if storage.remove_item(&format!("traverse_{key}")).is_err() {
+            bevy::log::warn!("Failed to remove {key} from localStorage");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::{gltf::GltfExtras, prelude::*, utils::HashMap};
+use bevy_fps_controller::controller::LogicalPlayer;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::{levels::GameLevel, util::all_children, ClientSet, GameLoading};
+
+pub struct TriggersPlugin;
+impl Plugin for TriggersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TriggerEntered>()
+            .add_event::<TriggerExited>()
+            .init_resource::<PlayerTriggers>()
+            .add_systems(
+                (spawn_scene_triggers, detect_triggers)
+                    .chain()
+                    .distributive_run_if(in_state(GameLoading::Loaded))
+                    .before(ClientSet::Ui),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TriggerKind {
+    Teleporter,
+    Clock,
+    KillZone,
+    /// Placed by the pickups module, not found by node name
+    Pickup,
+}
+
+/// Nodes are named like "teleporter", "teleporter BFA1" or "killzone.001".
+/// If a level name is given, the node is only used in that level.
+/// Returns the lowercase node type without the blender duplicate suffix.
+fn node_type_for_level(name: &str, level: &GameLevel) -> Option<String> {
+    let name = name.to_lowercase();
+    let mut parts = name.split_whitespace();
+    let node_type = parts.next()?;
+    if let Some(for_level) = parts.next() {
+        if for_level != format!("{:?}", level).to_lowercase() {
+            return None;
+        }
+    }
+    node_type.split('.').next().map(String::from)
+}
+
+impl TriggerKind {
+    pub fn from_node_name(name: &str, level: &GameLevel) -> Option<TriggerKind> {
+        match node_type_for_level(name, level)?.as_str() {
+            "teleporter" => Some(TriggerKind::Teleporter),
+            "clock" => Some(TriggerKind::Clock),
+            "killzone" => Some(TriggerKind::KillZone),
+            _ => None,
+        }
+    }
+
+    /// Radius used for triggers authored as empties
+    pub fn default_radius(&self) -> f32 {
+        match self {
+            TriggerKind::Teleporter => 8.0,
+            TriggerKind::Clock => 1.5,
+            TriggerKind::KillZone => 5.0,
+            TriggerKind::Pickup => 1.0,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Trigger(pub TriggerKind);
+
+pub struct TriggerEntered {
+    pub trigger: Entity,
+    pub kind: TriggerKind,
+}
+
+pub struct TriggerExited {
+    pub trigger: Entity,
+    pub kind: TriggerKind,
+}
+
+/// The triggers the player is currently standing in
+#[derive(Resource, Default)]
+pub struct PlayerTriggers(pub HashMap<Entity, TriggerKind>);
+
+impl PlayerTriggers {
+    pub fn contains(&self, kind: TriggerKind) -> bool {
+        self.0.values().any(|k| *k == kind)
+    }
+}
+
+/// Scan the children of this scene for trigger and player spawn nodes
+#[derive(Component)]
+pub struct SpawnTriggers;
+
+/// Where the player is placed when entering a level. Faces along the node's forward (-Z)
+#[derive(Component)]
+pub struct PlayerSpawn(pub GameLevel);
+
+#[derive(Deserialize)]
+struct TriggerExtras {
+    radius: Option<f32>,
+}
+
+/// Sensor collider for a trigger that isn't backed by a mesh
+pub fn trigger_sensor(kind: TriggerKind, radius: f32) -> impl Bundle {
+    (
+        Trigger(kind),
+        Collider::ball(radius),
+        // Ignore the scale of the node, radius is in world units
+        ColliderScale::Absolute(Vec3::ONE),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+    )
+}
+
+pub fn spawn_scene_triggers(
+    mut commands: Commands,
+    scene_entities: Query<Entity, With<SpawnTriggers>>,
+    children_query: Query<&Children>,
+    nodes: Query<(&Name, Option<&GltfExtras>)>,
+    mesh_handles: Query<&Handle<Mesh>>,
+    meshes: Res<Assets<Mesh>>,
+    level: Res<State<GameLevel>>,
+) {
+    for entity in scene_entities.iter() {
+        if let Ok(children) = children_query.get(entity) {
+            all_children(children, &children_query, &mut |entity| {
+                if let Ok((name, extras)) = nodes.get(entity) {
+                    if node_type_for_level(name, &level.0).as_deref() == Some("playerspawn") {
+                        commands.entity(entity).insert(PlayerSpawn(level.0.clone()));
+                    }
+                    if let Some(kind) = TriggerKind::from_node_name(name, &level.0) {
+                        if !add_mesh_triggers(
+                            &mut commands,
+                            entity,
+                            kind,
+                            &children_query,
+                            &mesh_handles,
+                            &meshes,
+                        ) {
+                            // Empty, use a sphere
+                            let radius = extras
+                                .and_then(|extras| {
+                                    serde_json::from_str::<TriggerExtras>(&extras.value).ok()
+                                })
+                                .and_then(|extras| extras.radius)
+                                .unwrap_or(kind.default_radius());
+                            commands.entity(entity).insert(trigger_sensor(kind, radius));
+                        }
+                    }
+                }
+            });
+            commands.entity(entity).remove::<SpawnTriggers>();
+        }
+    }
+}
+
+/// Turns the mesh primitives under a trigger node into sensors, returns false if there weren't any
+fn add_mesh_triggers(
+    commands: &mut Commands,
+    node: Entity,
+    kind: TriggerKind,
+    children_query: &Query<&Children>,
+    mesh_handles: &Query<&Handle<Mesh>>,
+    meshes: &Assets<Mesh>,
+) -> bool {
+    let mut found = false;
+    if let Ok(primitives) = children_query.get(node) {
+        for primitive in primitives {
+            if let Some(mesh) = mesh_handles
+                .get(*primitive)
+                .ok()
+                .and_then(|h| meshes.get(h))
+            {
+                if let Some(collider) = Collider::from_bevy_mesh(
+                    mesh,
+                    &ComputedColliderShape::ConvexDecomposition(default()),
+                ) {
+                    commands.entity(*primitive).insert((
+                        Trigger(kind),
+                        collider,
+                        Sensor,
+                        ActiveEvents::COLLISION_EVENTS,
+                        Visibility::Hidden,
+                    ));
+                    found = true;
+                }
+            }
+        }
+    }
+    found
+}
+
+pub fn detect_triggers(
+    mut collision_events: EventReader<CollisionEvent>,
+    triggers: Query<&Trigger>,
+    players: Query<(), With<LogicalPlayer>>,
+    mut player_triggers: ResMut<PlayerTriggers>,
+    mut entered: EventWriter<TriggerEntered>,
+    mut exited: EventWriter<TriggerExited>,
+) {
+    for event in collision_events.iter() {
+        let (a, b, started) = match event {
+            CollisionEvent::Started(a, b, _) => (*a, *b, true),
+            CollisionEvent::Stopped(a, b, _) => (*a, *b, false),
+        };
+        let trigger = if players.contains(a) {
+            b
+        } else if players.contains(b) {
+            a
+        } else {
+            continue;
+        };
+        if started {
+            if let Ok(Trigger(kind)) = triggers.get(trigger) {
+                player_triggers.0.insert(trigger, *kind);
+                entered.send(TriggerEntered {
+                    trigger,
+                    kind: *kind,
+                });
+            }
+        } else if let Some(kind) = player_triggers.0.remove(&trigger) {
+            exited.send(TriggerExited { trigger, kind });
+        }
+    }
+    // Triggers that were despawned with their level don't always report that they stopped
+    player_triggers.0.retain(|trigger, kind| {
+        let exists = triggers.contains(*trigger);
+        if !exists {
+            exited.send(TriggerExited {
+                trigger: *trigger,
+                kind: *kind,
+            });
+        }
+        exists
+    });
+}
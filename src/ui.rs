@@ -7,64 +7,69 @@ use bevy_egui::{
     *,
 };
 use bevy_fps_controller::controller::{FpsController, RenderPlayer};
-use bevy_rapier3d::prelude::Velocity;
 use iyes_progress::ProgressCounter;
+use serde::{Deserialize, Serialize};
 
+use crate::ui::egui::TextStyle::Body;
 use crate::ui::egui::TextStyle::Heading;
 use crate::ui::egui::TextStyle::Monospace;
 use crate::ui::egui::TextStyle::Small;
-use crate::{character_controller::JUMP_SPEED, ui::egui::TextStyle::Body};
-use crate::{levels::GameLevel, units::UnitData, GameLoading, Health};
+use crate::{
+    damage::{DamageSet, Died},
+    input::{Action, ActionState, InputBindings, Rebinding, UiWantsPointer},
+    levels::{manifest::Levels, GameLevel},
+    pause::SettingClock,
+    player::{player_shoot, teleport_player, PlayerGun, TeleportPlayer},
+    run_timer::{RunTimer, SplitRecords},
+    save::DiscoveredCodes,
+    settings::{DifficultyOverride, KeyBindings, Settings},
+    triggers::{PlayerTriggers, TriggerKind},
+    units::UnitData,
+    AppState, ClientSet, GameLoading, Health,
+};
 use crate::{ui::egui::TextStyle::Button, units::Difficulty};
 
 pub struct GameUiPlugin;
 impl Plugin for GameUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(EguiPlugin)
-            .add_system(ui_system.run_if(in_state(GameLoading::Loaded)))
+            .add_event::<MenuCommand>()
+            .add_systems(
+                (
+                    ui_system
+                        .in_set(ClientSet::Ui)
+                        .run_if(in_state(AppState::InGame)),
+                    run_menu_commands.after(ui_system).before(teleport_player),
+                    update_ui_wants_pointer
+                        .after(ui_system)
+                        .before(player_shoot),
+                    drones_dead_message.after(DamageSet),
+                )
+                    .distributive_run_if(in_state(GameLoading::Loaded)),
+            )
             .add_system(loading_ui.run_if(in_state(GameLoading::AssetLoading)))
             .insert_resource(PlayerCode::default())
-            .insert_resource(SettingClock::default())
             .insert_resource(TextFeed::default())
-            .insert_resource(GameElapsedTime::default())
-            .insert_resource(HasEnteredControlRoom::default())
-            .insert_resource(FinishedGame::default())
-            .insert_resource(AudioVolumes::default());
+            .insert_resource(HasEnteredControlRoom::default());
     }
 }
 
 #[derive(Resource, Default)]
 pub struct PlayerCode(pub String);
-#[derive(Resource, Default)]
-pub struct SettingClock(pub bool);
 
 #[derive(Resource, Default)]
 pub struct TextFeed(pub String);
 
-#[derive(Resource, Default)]
-pub struct GameElapsedTime(pub Option<f32>);
-
 #[derive(Resource, Default)]
 pub struct HasEnteredControlRoom(pub bool);
 
-#[derive(Resource)]
-pub struct AudioVolumes {
-    pub sfx: f32,
-    pub music: f32,
-}
-
-impl Default for AudioVolumes {
-    fn default() -> Self {
-        AudioVolumes {
-            sfx: 0.5,
-            music: 0.5,
-        }
-    }
+/// Gameplay changes made from the in game menus. Sent as events so replays can record them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MenuCommand {
+    SetClock(String),
+    RestartLevel,
 }
 
-#[derive(Resource, Default)]
-pub struct FinishedGame(pub (bool, f32));
-
 impl TextFeed {
     pub fn push(&mut self, text: &str) {
         self.0 = format!("{}\n\n> {}", self.0, text)
@@ -72,39 +77,44 @@ impl TextFeed {
 }
 
 pub fn ui_system(
-    mut commands: Commands,
     mut contexts: EguiContexts,
-    mut next_level: ResMut<NextState<GameLevel>>,
+    mut teleports: EventWriter<TeleportPlayer>,
     level: Res<State<GameLevel>>,
-    mut player: Query<(&mut Transform, &mut Velocity, &mut FpsController)>,
+    mut player: Query<&mut FpsController>,
     mut windows: Query<&mut Window>,
-    mut health: Query<&mut Health, With<RenderPlayer>>,
+    health: Query<(&Health, Option<&PlayerGun>), With<RenderPlayer>>,
     mut player_code: ResMut<PlayerCode>,
     units: Query<Entity, With<UnitData>>,
     mut setting_clock: ResMut<SettingClock>,
-    mut text_feed: ResMut<TextFeed>,
-    keys: Res<Input<KeyCode>>,
-    mut one_bot_left: Local<bool>,
-    mut difficulty: ResMut<Difficulty>,
-    end_game_and_audio: (
-        Res<GameElapsedTime>,
-        Res<FinishedGame>,
-        ResMut<AudioVolumes>,
+    text_feed: Res<TextFeed>,
+    action_state: Res<ActionState>,
+    mut settings: ResMut<Settings>,
+    mut difficulty_override: ResMut<DifficultyOverride>,
+    game_progress: (
+        Res<RunTimer>,
+        Res<SplitRecords>,
+        Levels,
+        Res<PlayerTriggers>,
+        Res<DiscoveredCodes>,
+        ResMut<Rebinding>,
+        EventWriter<MenuCommand>,
     ),
     time: Res<Time>,
+    mut was_setting_clock: Local<bool>,
 ) {
-    let (game_time, game_finished, mut audio_volumes) = end_game_and_audio;
+    let (
+        run_timer,
+        records,
+        levels,
+        player_triggers,
+        discovered_codes,
+        mut rebinding,
+        mut menu_commands,
+    ) = game_progress;
+    let level_props = levels.get(&level.0);
     let drones_remaining = units.iter().count();
-    if drones_remaining == 1 {
-        *one_bot_left = true;
-    }
-    if *one_bot_left && drones_remaining == 0 && level.0.show_drones_dead_msg() {
-        text_feed.push("Looks like all the drones have been eliminated, find the teleporter and continue to the next sector.");
-        *one_bot_left = false;
-    }
-    let was_setting_clock = setting_clock.0;
     let mut window = windows.single_mut();
-    if let Some((mut transform, mut velocity, mut fps_controller)) = player.iter_mut().next() {
+    if let Some(mut fps_controller) = player.iter_mut().next() {
         let ctx = contexts.ctx_mut();
         ctx.set_visuals(get_visuals());
         let frame = egui::Frame {
@@ -126,22 +136,38 @@ pub fn ui_system(
             .frame(frame)
             .show(contexts.ctx_mut(), |ui| {
                 ui.vertical_centered_justified(|ui| {
-                    if let Some(health) = &health.iter().next() {
-                        if game_finished.0 .0 {
-                            if let Some(game_time) = game_time.0 {
-                                ui.label(format!(
-                                    "TIME ELAPSED {:.1}",
-                                    game_finished.0 .1 - game_time
-                                ));
-                            }
-                        } else {
-                            if let Some(game_time) = game_time.0 {
-                                ui.label(format!(
-                                    "TIME ELAPSED {:.1}",
-                                    time.elapsed_seconds() - game_time
-                                ));
+                    if let Some((health, gun)) = &health.iter().next() {
+                        let now = time.elapsed_seconds();
+                        if let Some(elapsed) = run_timer.elapsed(now) {
+                            ui.label(format!("TIME ELAPSED {:.1}", elapsed));
+                            if let Some(delta) = records
+                                .personal_best
+                                .as_ref()
+                                .and_then(|pb| run_timer.delta(pb, now))
+                            {
+                                let color = if delta <= 0.0 {
+                                    Color32::GREEN
+                                } else {
+                                    Color32::RED
+                                };
+                                ui.colored_label(color, format!("PB {:+.1}", delta));
                             }
+                        }
+                        if !run_timer.is_finished() {
                             ui.label(format!("HEALTH {}", (health.0 * 100.0).round() as i32));
+                            if let Some(gun) = gun {
+                                if let Some(carried) = gun.carried() {
+                                    ui.label(carried.def.name.to_uppercase());
+                                    if gun.is_reloading() {
+                                        ui.label("RELOADING");
+                                    } else {
+                                        ui.label(format!(
+                                            "AMMO {} / {}",
+                                            carried.magazine, carried.reserve
+                                        ));
+                                    }
+                                }
+                            }
                             ui.label(format!("{} DRONES REMAINING", drones_remaining));
                         }
                     }
@@ -161,17 +187,26 @@ pub fn ui_system(
                         if ui.text_edit_singleline(&mut player_code.0).changed() {
                             player_code.0 = strip_non_numeric(&player_code.0);
                         }
-                        if ui.button("SET CLOCK").clicked() {
-                            if let Some(level) = GameLevel::teleporter_code(&player_code.0) {
-                                dbg!(&level, &player_code.0);
-                                player_code.0 = String::new();
-                                setting_clock.0 = false;
-                                teleport_dest = Some(level);
+                        // For entering codes without a keyboard
+                        ui.horizontal(|ui| {
+                            for digit in '0'..='9' {
+                                if ui.button(digit.to_string()).clicked() {
+                                    player_code.0.push(digit);
+                                }
                             }
+                            if ui.button("DEL").clicked() {
+                                player_code.0.pop();
+                            }
+                        });
+                        if ui.button("SET CLOCK").clicked() {
+                            menu_commands.send(MenuCommand::SetClock(player_code.0.clone()));
                         }
                         if ui.button("CLOSE").clicked() {
                             setting_clock.0 = false;
                         }
+                        if !discovered_codes.0.is_empty() {
+                            ui.label(format!("KNOWN CODES {}", discovered_codes.0.join(" ")));
+                        }
                     })
                 });
         }
@@ -186,40 +221,13 @@ pub fn ui_system(
                 .show(contexts.ctx_mut(), |ui| {
                     ui.vertical_centered_justified(|ui| {
                         if ui.button("RESTART LEVEL").clicked() {
-                            next_level.set(level.0.clone());
-                            teleport_dest = Some(level.0.clone());
+                            menu_commands.send(MenuCommand::RestartLevel);
                         }
-                        let easy = Difficulty::Easy == *difficulty;
-                        let medium = Difficulty::Medium == *difficulty;
-                        let hard = Difficulty::Hard == *difficulty;
-                        let ultra = Difficulty::Ultra == *difficulty;
-                        if ui.radio(easy, "EASY").clicked() {
-                            *difficulty = Difficulty::Easy;
-                        }
-                        if ui.radio(medium, "MEDIUM").clicked() {
-                            *difficulty = Difficulty::Medium;
-                        }
-                        if ui.radio(hard, "HARD").clicked() {
-                            *difficulty = Difficulty::Hard;
-                        }
-                        if ui.radio(ultra, "ULTRA").clicked() {
-                            *difficulty = Difficulty::Ultra;
-                        }
-                        let mut sens = fps_controller.sensitivity * 1000.0;
-                        if ui
-                            .add(Slider::new(&mut sens, 0.1..=5.0).text("Mouse Sensitivity"))
-                            .changed()
-                        {
-                            fps_controller.sensitivity = sens / 1000.0;
-                        }
-                        ui.add(Slider::new(&mut audio_volumes.sfx, 0.0..=1.0).text("SFX Volume"));
-                        ui.add(
-                            Slider::new(&mut audio_volumes.music, 0.0..=1.0).text("Music Volume"),
-                        );
+                        settings_ui(ui, &mut settings, &mut difficulty_override, &mut rebinding);
                     })
                 });
         } else {
-            if level.0.clock_position_close_enough(transform.translation) {
+            if player_triggers.contains(TriggerKind::Clock) {
                 egui::Window::new("set clock text")
                     .title_bar(false)
                     .collapsible(false)
@@ -230,16 +238,19 @@ pub fn ui_system(
                     .frame(frame)
                     .show(contexts.ctx_mut(), |ui| {
                         ui.vertical_centered_justified(|ui| {
-                            ui.label("PRESS E TO SET CLOCK");
+                            ui.label(format!(
+                                "PRESS {} TO SET CLOCK",
+                                settings.bindings.label(Action::Interact)
+                            ));
                             #[cfg(not(debug_assertions))]
-                            if keys.just_pressed(KeyCode::E) {
+                            if action_state.just_pressed(Action::Interact) {
                                 setting_clock.0 = !setting_clock.0
                             }
                         })
                     });
             }
-            if drones_remaining == 0 && level.0.teleporter_pos_close_enough(transform.translation) {
-                teleport_dest = Some(level.0.teleporter_dest());
+            if drones_remaining == 0 && player_triggers.contains(TriggerKind::Teleporter) {
+                teleport_dest = Some(level_props.teleporter_dest.clone());
             }
             let ctx = contexts.ctx_mut();
             let mut visuals = get_visuals();
@@ -254,34 +265,30 @@ pub fn ui_system(
                 .frame(frame)
                 .show(contexts.ctx_mut(), |ui| {
                     ui.vertical_centered_justified(|ui| {
-                        ui.label("PRESS TAB FOR MENU");
+                        ui.label(format!(
+                            "PRESS {} FOR MENU",
+                            settings.bindings.label(Action::Menu)
+                        ));
                     })
                 });
         }
         #[cfg(debug_assertions)]
-        if keys.just_pressed(KeyCode::E) {
+        if action_state.just_pressed(Action::Interact) {
             setting_clock.0 = !setting_clock.0;
         }
-        if was_setting_clock && !setting_clock.0 {
+        // Compared to last frame, the clock can also be closed by a menu command
+        if *was_setting_clock && !setting_clock.0 {
             fps_controller.enable_input = true;
             window.cursor.grab_mode = CursorGrabMode::Locked;
             window.cursor.visible = false;
-        } else if !was_setting_clock && setting_clock.0 {
+        } else if !*was_setting_clock && setting_clock.0 {
             fps_controller.enable_input = false;
             window.cursor.grab_mode = CursorGrabMode::None;
             window.cursor.visible = true;
         }
+        *was_setting_clock = setting_clock.0;
         if let Some(teleport_dest) = teleport_dest {
-            teleport(
-                &mut fps_controller,
-                &mut health,
-                teleport_dest,
-                &mut next_level,
-                &mut velocity,
-                &mut transform,
-                &units,
-                &mut commands,
-            );
+            teleports.send(TeleportPlayer(teleport_dest));
         }
         egui::Window::new("text_feed")
             .title_bar(false)
@@ -302,37 +309,126 @@ pub fn ui_system(
     }
 }
 
-fn teleport(
-    fps_controller: &mut FpsController,
-    health: &mut Query<&mut Health, With<RenderPlayer>>,
-    level: GameLevel,
-    next_level: &mut NextState<GameLevel>,
-    velocity: &mut Velocity,
-    transform: &mut Transform,
-    units: &Query<Entity, With<UnitData>>,
-    commands: &mut Commands,
+/// Once the last drone in a level is killed
+fn drones_dead_message(
+    mut died_events: EventReader<Died>,
+    units: Query<Entity, With<UnitData>>,
+    level: Res<State<GameLevel>>,
+    levels: Levels,
+    mut text_feed: ResMut<TextFeed>,
 ) {
-    fps_controller.gravity = 0.0;
-    if let Some(mut health) = health.iter_mut().next() {
-        health.0 = 1.0;
+    let died: Vec<Entity> = died_events
+        .iter()
+        .map(|died| died.entity)
+        .filter(|entity| units.contains(*entity))
+        .collect();
+    if died.is_empty() || !levels.get(&level.0).show_drones_dead_msg {
+        return;
     }
-    if level.player_can_jump() {
-        fps_controller.jump_speed = JUMP_SPEED;
-    } else {
-        fps_controller.jump_speed = 0.0;
+    // Dead units are only despawned once commands are applied
+    if units.iter().all(|unit| died.contains(&unit)) {
+        text_feed.push("Looks like all the drones have been eliminated, find the teleporter and continue to the next sector.");
     }
-    next_level.set(level.clone());
+}
 
-    velocity.linvel = Vec3::ZERO;
-    transform.translation = level.spawn_pos();
-    for unit in units {
-        if commands.get_entity(unit).is_some() {
-            commands.entity(unit).despawn_recursive();
+pub fn run_menu_commands(
+    mut menu_commands: EventReader<MenuCommand>,
+    mut teleports: EventWriter<TeleportPlayer>,
+    level: Res<State<GameLevel>>,
+    levels: Levels,
+    mut player_code: ResMut<PlayerCode>,
+    mut setting_clock: ResMut<SettingClock>,
+    mut discovered_codes: ResMut<DiscoveredCodes>,
+) {
+    for command in menu_commands.iter() {
+        match command {
+            MenuCommand::SetClock(code) => {
+                if let Some(level) = levels.teleporter_code(code) {
+                    debug!("Clock code {code} teleports to {level:?}");
+                    discovered_codes.add(code);
+                    player_code.0 = String::new();
+                    setting_clock.0 = false;
+                    teleports.send(TeleportPlayer(level));
+                }
+            }
+            MenuCommand::RestartLevel => teleports.send(TeleportPlayer(level.0.clone())),
         }
     }
 }
 
-fn set_text_styles(ctx: &mut egui::Context) {
+fn update_ui_wants_pointer(
+    mut contexts: EguiContexts,
+    mut ui_wants_pointer: ResMut<UiWantsPointer>,
+) {
+    ui_wants_pointer.0 = contexts.ctx_mut().wants_pointer_input();
+}
+
+/// Difficulty, look, audio and control bindings. Used by the tab menu and the main menu
+pub fn settings_ui(
+    ui: &mut egui::Ui,
+    settings: &mut ResMut<Settings>,
+    difficulty_override: &mut ResMut<DifficultyOverride>,
+    rebinding: &mut Rebinding,
+) {
+    // Edit a copy so settings are only marked changed (and saved) on edits
+    let mut new_settings = settings.clone();
+    // Show the difficulty being played, picking one goes back to the one in settings
+    let difficulty = difficulty_override.0.unwrap_or(new_settings.difficulty);
+    let easy = Difficulty::Easy == difficulty;
+    let medium = Difficulty::Medium == difficulty;
+    let hard = Difficulty::Hard == difficulty;
+    let ultra = Difficulty::Ultra == difficulty;
+    let mut picked = None;
+    if ui.radio(easy, "EASY").clicked() {
+        picked = Some(Difficulty::Easy);
+    }
+    if ui.radio(medium, "MEDIUM").clicked() {
+        picked = Some(Difficulty::Medium);
+    }
+    if ui.radio(hard, "HARD").clicked() {
+        picked = Some(Difficulty::Hard);
+    }
+    if ui.radio(ultra, "ULTRA").clicked() {
+        picked = Some(Difficulty::Ultra);
+    }
+    if let Some(picked) = picked {
+        new_settings.difficulty = picked;
+        difficulty_override.0 = None;
+    }
+    let mut sens = new_settings.sensitivity * 1000.0;
+    if ui
+        .add(Slider::new(&mut sens, 0.1..=5.0).text("Mouse Sensitivity"))
+        .changed()
+    {
+        new_settings.sensitivity = sens / 1000.0;
+    }
+    ui.add(Slider::new(&mut new_settings.stick.look_speed, 0.5..=8.0).text("Stick Sensitivity"));
+    ui.checkbox(&mut new_settings.invert_y, "Invert Y");
+    ui.checkbox(&mut new_settings.show_ghost, "Show PB Ghost");
+    ui.add(Slider::new(&mut new_settings.fov, 50.0..=110.0).text("FOV"));
+    ui.add(Slider::new(&mut new_settings.sfx_volume, 0.0..=1.0).text("SFX Volume"));
+    ui.add(Slider::new(&mut new_settings.music_volume, 0.0..=1.0).text("Music Volume"));
+    ui.label("CONTROLS");
+    for action in Action::ALL {
+        let text = if rebinding.0 == Some(action) {
+            format!("{} PRESS A KEY OR BUTTON", action.label())
+        } else {
+            format!("{} {}", action.label(), new_settings.bindings.label(action))
+        };
+        if ui.button(text).clicked() {
+            rebinding.0 = Some(action);
+        }
+    }
+    if ui.button("RESET CONTROLS").clicked() {
+        new_settings.key_bindings = KeyBindings::default();
+        new_settings.bindings = InputBindings::default();
+    }
+    if new_settings != **settings {
+        **settings = new_settings;
+    }
+}
+
+pub fn set_text_styles(ctx: &mut egui::Context) {
     let mut style = (*ctx.style()).clone();
     style.text_styles = [
         (Heading, FontId::new(30.0, FontFamily::Monospace)),
@@ -345,7 +441,7 @@ fn set_text_styles(ctx: &mut egui::Context) {
     ctx.set_style(style);
 }
 
-fn get_visuals() -> egui::Visuals {
+pub fn get_visuals() -> egui::Visuals {
     let mut visuals = egui::Visuals::dark();
     visuals.override_text_color = Some(Color32::WHITE);
     visuals.extreme_bg_color = Color32::from_rgba_unmultiplied(0, 0, 0, 192);
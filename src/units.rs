@@ -1,20 +1,35 @@
 use std::time::Duration;
 
-use bevy::{math::vec3, prelude::*};
-use bevy_rapier3d::prelude::{Collider, QueryFilter, RapierContext};
+use bevy::{gltf::GltfExtras, math::vec3, prelude::*};
+use bevy_rapier3d::prelude::{
+    CharacterAutostep, CharacterLength, Collider, KinematicCharacterController,
+    KinematicCharacterControllerOutput, QueryFilterFlags, RigidBody,
+};
 use rand::distributions::WeightedIndex;
 use rand::prelude::Distribution;
-use rand::seq::SliceRandom;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::assets::{AudioAssets, PropAssets};
-use crate::character_controller::{LogicalPlayerEntity, ShootableByUnit};
-use crate::player::Projectile;
-use crate::ui::AudioVolumes;
+use crate::assets::PropAssets;
+use crate::character_controller::{LogicalPlayerEntity, GRAVITY};
+use crate::damage::{DamageEvent, DamageKind, DamageSet, Died};
+use crate::navmesh::NavMesh;
+use crate::pause::PauseState;
+use crate::player::{Projectile, ProjectileHit};
+use crate::sfx::{PlaySfx, Sfx};
 use crate::util::all_children;
 use crate::Health;
-use crate::{assets::UnitAssets, GameLoading, GameRng};
-use bevy_kira_audio::AudioControl;
+use crate::{GameLoading, GameRng};
+
+use self::{
+    ai::UnitAction,
+    kinds::{spawn_kind_name, UnitAnimations, UnitKinds, UnitWeapon},
+    perception::{Awareness, Heard},
+};
+
+pub mod ai;
+pub mod kinds;
+pub mod perception;
 
 pub struct UnitsPlugin;
 impl Plugin for UnitsPlugin {
@@ -23,17 +38,30 @@ impl Plugin for UnitsPlugin {
             (
                 spawn_enemies,
                 play_animations,
-                roam,
+                ai::decide.after(perception::see),
                 setup_anim_player_refs,
                 face_dest_pos,
                 move_to_dest,
-                target_shootables,
+                perception::hear.before(perception::see),
+                perception::see,
                 shoot_stuff,
-                blowup,
-                damage_player,
             )
-                .distributive_run_if(in_state(GameLoading::Loaded)),
+                .distributive_run_if(in_state(GameLoading::Loaded))
+                .distributive_run_if(in_state(PauseState::Running))
+                .in_schedule(CoreSchedule::FixedUpdate),
         )
+        // Projectiles move every frame, so hits are handled every frame. What units hear is
+        // queued for the fixed step
+        .add_systems(
+            (
+                damage_player.before(DamageSet),
+                blowup.after(DamageSet),
+                perception::queue_heard.after(DamageSet),
+            )
+                .distributive_run_if(in_state(GameLoading::Loaded))
+                .distributive_run_if(in_state(PauseState::Running)),
+        )
+        .init_resource::<Heard>()
         .init_resource::<Difficulty>();
         //.add_system(spawn_some_units.in_schedule(OnEnter(GameLoading::Loaded)));
     }
@@ -58,7 +86,7 @@ pub fn setup_anim_player_refs(
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Deserialize)]
 pub enum UnitsStates {
     Walk,
     Idle,
@@ -70,32 +98,15 @@ pub enum UnitsStates {
 }
 
 impl UnitsStates {
-    pub fn rng_pick(rng: &mut GameRng) -> UnitsStates {
-        let items = [
-            (UnitsStates::Walk, 100),
-            (UnitsStates::Idle, 15),
-            (UnitsStates::Bob, 7),
-            (UnitsStates::Bonk, 1),
-            (UnitsStates::Fire, 1),
-            (UnitsStates::WalkLazy, 20),
-            (UnitsStates::Stop, 100),
-        ];
-        let dist = WeightedIndex::new(items.iter().map(|item| item.1)).unwrap();
-        items[dist.sample(&mut rng.0)].0
-    }
-
-    pub fn rng_pick_attacking(rng: &mut GameRng) -> UnitsStates {
-        let items = [
-            (UnitsStates::Walk, 40),
-            (UnitsStates::Bob, 7),
-            (UnitsStates::Fire, 100),
-            (UnitsStates::WalkLazy, 20),
-        ];
-        let dist = WeightedIndex::new(items.iter().map(|item| item.1)).unwrap();
-        items[dist.sample(&mut rng.0)].0
+    /// Stops if the weights are empty or all zero
+    pub fn rng_pick(rng: &mut GameRng, weights: &[(UnitsStates, u32)]) -> UnitsStates {
+        match WeightedIndex::new(weights.iter().map(|item| item.1)) {
+            Ok(dist) => weights[dist.sample(&mut rng.0)].0,
+            Err(_) => UnitsStates::Stop,
+        }
     }
 
-    pub fn get_anim(&self, ass: &UnitAssets) -> Option<Handle<AnimationClip>> {
+    pub fn get_anim(&self, ass: &UnitAnimations) -> Option<Handle<AnimationClip>> {
         Some(match self {
             UnitsStates::Walk => ass.walk.clone(),
             UnitsStates::Idle => ass.idle.clone(),
@@ -136,7 +147,8 @@ impl UnitsStates {
 pub struct UnitData {
     pub spawn: Vec3,
     pub max_radius: f32,
-    pub dest: Vec3,
+    /// Waypoints left to walk to, the next one first
+    pub path: Vec<Vec3>,
     pub target_to_shoot: Option<Vec3>,
     pub target_to_apply_damage: Option<Entity>,
     pub current_state: UnitsStates,
@@ -145,78 +157,44 @@ pub struct UnitData {
     pub speed: f32,
     pub arrived: bool,
     pub init: bool, // for some reason they disappear if they don't walk first
-    pub range: f32,
+    pub weapon: UnitWeapon,
     pub fire_cooldown: f32,
-    pub fire_rate: f32,
+    pub max_health: f32,
+    pub action: UnitAction,
+    /// Whether there was a target to shoot last time the unit decided
+    pub had_target: bool,
+    pub last_known_target: Option<Vec3>,
+    /// Seconds until the last known target position is forgotten
+    pub memory_timer: f32,
+    /// Downwards speed while off the ground
+    pub fall_speed: f32,
 }
 
-#[derive(Component)]
-pub struct ChildAnimEntity(pub Entity);
+impl UnitData {
+    /// Firing in place, or strafing
+    pub fn is_firing(&self) -> bool {
+        matches!(self.current_state, UnitsStates::Fire) || self.action == UnitAction::Strafe
+    }
 
-fn roam(time: Res<Time>, mut units: Query<&mut UnitData>, mut rng: ResMut<GameRng>) {
-    for mut unit in &mut units {
-        unit.state_timer -= time.delta_seconds();
-        if unit.state_timer < 0.0 {
-            unit.current_state = if unit.init {
-                if unit.target_to_shoot.is_some() {
-                    UnitsStates::rng_pick_attacking(&mut rng)
-                } else {
-                    UnitsStates::rng_pick(&mut rng)
-                }
-            } else {
-                unit.init = true;
-                UnitsStates::Walk
-            };
-            match unit.current_state {
-                UnitsStates::Walk => {
-                    unit.state_timer = rng.gen_range(1.0..2.0);
-                    let r = unit.max_radius;
-                    unit.dest = vec3(
-                        unit.spawn.x + rng.gen_range(-r..r),
-                        unit.spawn.y,
-                        unit.spawn.z + rng.gen_range(-r..r),
-                    );
-                    unit.arrived = false;
-                }
-                UnitsStates::Idle => {
-                    unit.state_timer = rng.gen_range(1.0..2.0);
-                    unit.arrived = true;
-                }
-                UnitsStates::Bob => {
-                    unit.state_timer = rng.gen_range(1.0..2.0);
-                    unit.arrived = true;
-                }
-                UnitsStates::Bonk => {
-                    unit.state_timer = rng.gen_range(2.0..3.0);
-                    unit.arrived = true;
-                }
-                UnitsStates::Fire => {
-                    unit.state_timer = rng.gen_range(1.0..2.0);
-                    unit.arrived = true;
-                }
-                UnitsStates::WalkLazy => {
-                    unit.state_timer = rng.gen_range(1.0..2.0);
-                    let r = unit.max_radius;
-                    unit.dest = vec3(
-                        unit.spawn.x + rng.gen_range(-r..r),
-                        unit.spawn.y,
-                        unit.spawn.z + rng.gen_range(-r..r),
-                    );
-                    unit.arrived = false;
-                }
-                UnitsStates::Stop => {
-                    unit.state_timer = rng.gen_range(1.0..2.0);
-                    unit.arrived = true;
-                }
-            }
-        }
+    /// Follows the navmesh to `dest`, or walks straight there if the level doesn't have one.
+    /// Stays put if `dest` can't be reached
+    pub fn walk_to(&mut self, position: Vec3, dest: Vec3, navmesh: &NavMesh) {
+        self.path = if navmesh.is_empty() {
+            vec![vec3(dest.x, self.spawn.y, dest.z)]
+        } else {
+            navmesh.find_path(position, dest).unwrap_or_default()
+        };
+        self.arrived = self.path.is_empty();
     }
 }
 
+#[derive(Component)]
+pub struct ChildAnimEntity(pub Entity);
+
 #[derive(Component)]
 pub struct EnemySpawns;
 
-#[derive(Resource, Default, PartialEq, Eq)]
+#[derive(Resource, Default, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Difficulty {
     #[default]
     Easy,
@@ -240,46 +218,61 @@ pub fn spawn_enemies(
     mut commands: Commands,
     scene_entities: Query<Entity, With<EnemySpawns>>,
     children_query: Query<&Children>,
-    transforms: Query<(&Transform, &Name)>,
-    unit_assets: Res<UnitAssets>,
+    transforms: Query<(&Transform, &Name, Option<&GltfExtras>)>,
+    unit_kinds: UnitKinds,
+    asset_server: Res<AssetServer>,
     mut rng: ResMut<GameRng>,
 ) {
     for entity in scene_entities.iter() {
         if let Ok(children) = children_query.get(entity) {
             all_children(children, &children_query, &mut |entity| {
-                if let Ok((trans, name)) = transforms.get(entity) {
+                if let Ok((trans, name, extras)) = transforms.get(entity) {
                     if !name.to_lowercase().contains("enemyspawn") {
                         return; //return from closure
                     }
+                    let kind_name = spawn_kind_name(name, extras);
+                    let kind = match unit_kinds.get_or_default(kind_name.as_deref()) {
+                        Some(kind) => kind,
+                        None => return,
+                    };
                     let spawn_pos = trans.translation + Vec3::Y * 0.05;
                     commands
                         .spawn(SceneBundle {
-                            scene: unit_assets.unit1.clone(),
+                            scene: asset_server.load(&kind.scene),
                             transform: Transform::from_translation(spawn_pos),
                             ..default()
                         })
                         .insert(UnitData {
                             spawn: spawn_pos,
-                            max_radius: 2.0,
-                            dest: spawn_pos,
+                            max_radius: kind.roam_radius,
+                            path: Vec::new(),
                             current_state: UnitsStates::Stop,
                             state_timer: rng.gen_range(1.5..2.5),
                             current_clip: None,
-                            speed: rng.gen_range(0.8..2.5),
+                            speed: kind.random_speed(&mut rng),
                             arrived: false,
                             init: false,
                             target_to_shoot: None,
                             target_to_apply_damage: None,
-                            range: 43.0,
+                            weapon: kind.weapon,
                             fire_cooldown: 1.0,
-                            fire_rate: 4.0,
+                            max_health: kind.health,
+                            action: UnitAction::Roam,
+                            had_target: false,
+                            last_known_target: None,
+                            memory_timer: 0.0,
+                            fall_speed: 0.0,
                         })
-                        .insert(Collider::capsule(
-                            vec3(0.0, 0.0, 0.0),
-                            vec3(0.0, 1.6, 0.0),
-                            0.4,
+                        .insert(kind.animations.load(&asset_server))
+                        .insert(kind.behavior.clone())
+                        .insert((kind.perception, Awareness::default()))
+                        .insert((
+                            RigidBody::KinematicPositionBased,
+                            // Bottom of the capsule at the unit's feet
+                            Collider::capsule(vec3(0.0, 0.4, 0.0), vec3(0.0, 1.6, 0.0), 0.4),
+                            unit_controller(),
                         ))
-                        .insert(Health(1.0));
+                        .insert((Health(kind.health), kind.armor));
                 }
             });
             commands.entity(entity).remove::<EnemySpawns>();
@@ -328,21 +321,20 @@ pub fn spawn_enemies(
 //}
 
 fn play_animations(
-    mut units: Query<(&mut UnitData, &ChildAnimEntity)>,
-    unit_assets: Res<UnitAssets>,
+    mut units: Query<(&mut UnitData, &UnitAnimations, &ChildAnimEntity)>,
     mut animation_players: Query<&mut AnimationPlayer>,
 ) {
-    for (unit, anim) in &mut units {
+    for (unit, animations, anim) in &mut units {
         if let Ok(mut player) = animation_players.get_mut(anim.0) {
             let clip = match unit.current_state {
                 UnitsStates::Walk | UnitsStates::WalkLazy => {
                     if !unit.arrived {
-                        unit.current_state.get_anim(&unit_assets)
+                        unit.current_state.get_anim(animations)
                     } else {
                         None
                     }
                 }
-                _ => unit.current_state.get_anim(&unit_assets),
+                _ => unit.current_state.get_anim(animations),
             };
             if let Some(clip) = clip {
                 let play = if let Some(current_clip) = unit.current_clip.clone() {
@@ -374,90 +366,91 @@ fn play_animations(
 pub fn face_dest_pos(mut unit_entities: Query<(&mut Transform, &UnitData)>) {
     for (mut trans, unit) in &mut unit_entities {
         match unit.current_state {
-            UnitsStates::Fire => {
+            _ if unit.is_firing() => {
                 if let Some(target_to_shoot) = unit.target_to_shoot {
-                    let look = vec3(target_to_shoot.x, unit.spawn.y, target_to_shoot.z);
+                    let look = vec3(target_to_shoot.x, trans.translation.y, target_to_shoot.z);
                     let new_trans = trans.looking_at(look, Vec3::Y);
                     trans.rotation = new_trans.rotation;
                 }
             }
             UnitsStates::Walk | UnitsStates::WalkLazy => {
-                let look = vec3(unit.dest.x, unit.spawn.y, unit.dest.z);
-                let new_trans = trans.looking_at(look, Vec3::Y);
-                trans.rotation = new_trans.rotation;
+                if let Some(next) = unit.path.first() {
+                    let look = vec3(next.x, trans.translation.y, next.z);
+                    if look.distance_squared(trans.translation) > 0.0001 {
+                        let new_trans = trans.looking_at(look, Vec3::Y);
+                        trans.rotation = new_trans.rotation;
+                    }
+                }
             }
             _ => {}
         }
     }
 }
 
-pub fn move_to_dest(mut unit_entities: Query<(&mut Transform, &mut UnitData)>) {
-    for (mut trans, mut unit) in &mut unit_entities {
-        match unit.current_state {
-            UnitsStates::Walk | UnitsStates::WalkLazy => {
-                if trans.translation.distance(unit.dest) > 0.1 {
-                    let dir = (unit.dest - trans.translation).normalize();
-                    trans.translation += dir * unit.speed * 0.005;
-                } else {
-                    unit.arrived = true;
-                }
-            }
-            _ => (),
-        }
+/// From a unit's origin to its head
+const HEAD_HEIGHT: f32 = 1.65;
+/// Distance per second at a unit speed of 1.0
+const WALK_SPEED: f32 = 0.3;
+/// Units step up onto things this high
+const STEP_HEIGHT: f32 = 0.4;
+
+fn horizontal(v: Vec3) -> Vec3 {
+    vec3(v.x, 0.0, v.z)
+}
+
+fn unit_controller() -> KinematicCharacterController {
+    KinematicCharacterController {
+        offset: CharacterLength::Absolute(0.02),
+        autostep: Some(CharacterAutostep {
+            max_height: CharacterLength::Absolute(STEP_HEIGHT),
+            min_width: CharacterLength::Absolute(0.2),
+            include_dynamic_bodies: false,
+        }),
+        max_slope_climb_angle: 45.0_f32.to_radians(),
+        min_slope_slide_angle: 30.0_f32.to_radians(),
+        snap_to_ground: Some(CharacterLength::Absolute(0.3)),
+        // Walk through triggers
+        filter_flags: QueryFilterFlags::EXCLUDE_SENSORS,
+        ..default()
     }
 }
 
-pub fn target_shootables(
-    mut unit_entities: Query<(Entity, &GlobalTransform, &mut UnitData)>,
-    shootables: Query<
-        (Entity, &GlobalTransform, Option<&LogicalPlayerEntity>),
-        With<ShootableByUnit>,
-    >,
-    rapier_context: Res<RapierContext>,
+/// Walks along the path and falls, through the character controller so units collide with
+/// the level
+pub fn move_to_dest(
+    fixed_time: Res<FixedTime>,
+    mut unit_entities: Query<(
+        &Transform,
+        &mut UnitData,
+        &mut KinematicCharacterController,
+        Option<&KinematicCharacterControllerOutput>,
+    )>,
 ) {
-    for (unit_entity, unit_trans, mut unit) in &mut unit_entities {
-        let unit_trans = unit_trans.compute_transform();
-        let mut closest_entity = None;
-        let mut closest_pos = vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY);
-        let mut closest_dist = f32::INFINITY;
-        let mut logical_player = None;
-        for (shootable, shootable_trans, logical_player_shootable) in &shootables {
-            let shootable_trans = shootable_trans.compute_transform();
-            let this_dist = shootable_trans.translation.distance(unit_trans.translation);
-            if this_dist < closest_dist {
-                closest_dist = this_dist;
-                closest_pos = shootable_trans.translation;
-                closest_entity = Some(shootable);
-                logical_player = logical_player_shootable;
-            }
-        }
-        unit.target_to_shoot = None;
-        unit.target_to_apply_damage = None;
-        if let Some(closest_entity) = closest_entity {
-            if closest_dist < unit.range {
-                let origin = unit_trans.translation + Vec3::Y * 1.65; // head level
-                let hit = rapier_context.cast_ray(
-                    origin,
-                    (closest_pos - origin).normalize(),
-                    f32::MAX,
-                    false,
-                    QueryFilter::default()
-                        .exclude_collider(unit_entity)
-                        .exclude_sensors(),
-                );
-                if let Some(hit) = hit {
-                    if hit.0 == closest_entity {
-                        unit.target_to_shoot = Some(closest_pos);
-                        unit.target_to_apply_damage = Some(closest_entity);
-                    } else if let Some(logical_player) = logical_player {
-                        if hit.0 == logical_player.0 {
-                            unit.target_to_shoot = Some(closest_pos);
-                            unit.target_to_apply_damage = Some(closest_entity);
-                        }
-                    }
+    let dt = fixed_time.period.as_secs_f32();
+    for (trans, mut unit, mut controller, output) in &mut unit_entities {
+        let mut movement = Vec3::ZERO;
+        match unit.current_state {
+            UnitsStates::Walk | UnitsStates::WalkLazy => match unit.path.first().copied() {
+                Some(next) if horizontal(next - trans.translation).length() > 0.1 => {
+                    let dir = horizontal(next - trans.translation).normalize();
+                    movement = dir * unit.speed * WALK_SPEED * dt;
                 }
-            }
+                Some(_) => {
+                    unit.path.remove(0);
+                    unit.arrived = unit.path.is_empty();
+                }
+                None => unit.arrived = true,
+            },
+            _ => (),
         }
+        if output.map_or(false, |output| output.grounded) {
+            unit.fall_speed = 0.0;
+        }
+        // Always pushed down a little, or the controller can't tell it's on the ground
+        unit.fall_speed += GRAVITY * dt;
+        movement.y -= unit.fall_speed * dt;
+        // Physics runs once a frame, there can be more than one fixed step in between
+        controller.translation = Some(controller.translation.unwrap_or_default() + movement);
     }
 }
 
@@ -465,29 +458,27 @@ pub fn shoot_stuff(
     mut commands: Commands,
     mut unit_entities: Query<(Entity, &GlobalTransform, &mut UnitData)>,
     mut target: Query<&GlobalTransform>,
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     props: Res<PropAssets>,
     difficulty: Res<Difficulty>,
-    audio_assets: Res<AudioAssets>,
-    mut rng: ResMut<GameRng>,
-    audio: Res<bevy_kira_audio::Audio>,
-    audio_volumes: Res<AudioVolumes>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
-    for (_unit_entity, unit_trans, mut unit) in &mut unit_entities {
-        unit.fire_cooldown -= unit.fire_rate * time.delta_seconds();
+    for (unit_entity, unit_trans, mut unit) in &mut unit_entities {
+        unit.fire_cooldown -= unit.weapon.fire_rate * fixed_time.period.as_secs_f32();
         if unit.fire_cooldown > 0.0 {
             continue;
         }
         let unit_trans = unit_trans.compute_transform();
-        if let UnitsStates::Fire = unit.current_state {
+        if unit.is_firing() {
             if let Some(target_to_apply_damage) = unit.target_to_apply_damage {
                 if let Some(target_to_shoot) = unit.target_to_shoot {
                     if let Ok(player_trans) = target.get_mut(target_to_apply_damage) {
                         unit.fire_cooldown = 1.0; //reset cooldown
 
                         // head level
-                        let start_pos =
-                            unit_trans.translation + Vec3::Y * 1.65 + unit_trans.right() * 0.2;
+                        let start_pos = unit_trans.translation
+                            + Vec3::Y * HEAD_HEIGHT
+                            + unit_trans.right() * 0.2;
                         // XD (so we don't come directly at players camera)
                         let target =
                             target_to_shoot - vec3(0.01, 0.1, 0.01) + player_trans.left() * 0.01;
@@ -500,26 +491,13 @@ pub fn shoot_stuff(
                                 ..default()
                             })
                             .insert(Projectile {
-                                speed: 100.0,
+                                speed: unit.weapon.projectile_speed,
                                 max_dist: 1000.0,
                                 dist_trav: 0.0,
+                                shooter: Some(unit_entity),
                             })
-                            .insert(DamagePlayer(difficulty.bot_dmg()));
-                        audio
-                            .play(
-                                [
-                                    audio_assets.enemygun1.clone(),
-                                    audio_assets.enemygun2.clone(),
-                                    audio_assets.enemygun3.clone(),
-                                    audio_assets.enemygun4.clone(),
-                                    audio_assets.enemygun5.clone(),
-                                ]
-                                .choose(&mut rng.0)
-                                .unwrap()
-                                .clone(),
-                            )
-                            .with_playback_rate(1.0)
-                            .with_volume(audio_volumes.sfx as f64 * 1.2);
+                            .insert(DamagePlayer(difficulty.bot_dmg() * unit.weapon.damage));
+                        sfx.send(PlaySfx(Sfx::EnemyGun));
                     }
                 }
             }
@@ -529,95 +507,69 @@ pub fn shoot_stuff(
 
 pub fn blowup(
     mut commands: Commands,
-    mut unit_entities: Query<(Entity, &GlobalTransform, &mut UnitData, &Health)>,
+    unit_entities: Query<&GlobalTransform, With<UnitData>>,
+    mut died_events: EventReader<Died>,
     mut rng: ResMut<GameRng>,
     props: Res<PropAssets>,
-    audio_assets: Res<AudioAssets>,
-    audio: Res<bevy_kira_audio::Audio>,
-    audio_volumes: Res<AudioVolumes>,
 ) {
-    for (entity, trans, _unit, health) in &mut unit_entities {
-        if health.0 <= 0.0 {
-            if commands.get_entity(entity).is_some() {
-                commands.entity(entity).despawn_recursive();
-            }
+    for died in died_events.iter() {
+        let trans = match unit_entities.get(died.entity) {
+            Ok(trans) => trans,
+            Err(_) => continue,
+        };
+        commands.entity(died.entity).despawn_recursive();
 
-            audio
-                .play(
-                    [
-                        audio_assets.enemyexplode1.clone(),
-                        audio_assets.enemyexplode2.clone(),
-                        audio_assets.enemyexplode3.clone(),
-                        audio_assets.enemyexplode4.clone(),
-                        audio_assets.enemyexplode5.clone(),
-                        audio_assets.enemyexplode6.clone(),
-                    ]
-                    .choose(&mut rng.0)
-                    .unwrap()
-                    .clone(),
-                )
-                .with_playback_rate(1.8)
-                .with_volume((audio_volumes.sfx * 1.1) as f64);
-            for _ in 0..16 {
-                let origin = trans.translation() + trans.up();
-                commands
-                    .spawn(SceneBundle {
-                        scene: props.projectile_lite_red.clone(),
-                        transform: Transform::from_translation(origin).looking_at(
-                            origin
-                                + vec3(
-                                    rng.gen_range(-1.0..1.0),
-                                    rng.gen_range(0.4..1.0),
-                                    rng.gen_range(-1.0..1.0),
-                                ),
-                            Vec3::Y,
-                        ),
-                        ..default()
-                    })
-                    .insert(Projectile {
-                        speed: 35.0,
-                        max_dist: 20.0,
-                        dist_trav: 0.0,
-                    });
-            }
+        for _ in 0..16 {
+            let origin = trans.translation() + trans.up();
+            commands
+                .spawn(SceneBundle {
+                    scene: props.projectile_lite_red.clone(),
+                    transform: Transform::from_translation(origin).looking_at(
+                        origin
+                            + vec3(
+                                rng.gen_range(-1.0..1.0),
+                                rng.gen_range(0.4..1.0),
+                                rng.gen_range(-1.0..1.0),
+                            ),
+                        Vec3::Y,
+                    ),
+                    ..default()
+                })
+                .insert(Projectile {
+                    speed: 35.0,
+                    max_dist: 20.0,
+                    dist_trav: 0.0,
+                    shooter: None,
+                });
         }
     }
 }
 
+/// Damage dealt to the player by a projectile that hits them
 #[derive(Component)]
-struct DamagePlayer(f32);
+pub struct DamagePlayer(pub f32);
 
 fn damage_player(
-    mut player: Query<(&GlobalTransform, &LogicalPlayerEntity, &mut Health)>,
-    mut projectiles: Query<(&GlobalTransform, &mut Projectile, &DamagePlayer)>,
-    audio_assets: Res<AudioAssets>,
-    mut rng: ResMut<GameRng>,
-    audio: Res<bevy_kira_audio::Audio>,
-    audio_volumes: Res<AudioVolumes>,
+    player: Query<(Entity, &LogicalPlayerEntity)>,
+    mut hits: EventReader<ProjectileHit>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
-    for (player_trans, _, mut health) in &mut player {
-        for (proj_trans, _proj, damage) in &mut projectiles {
-            if player_trans
-                .translation()
-                .distance(proj_trans.translation())
-                < 1.0
-            {
-                health.0 -= damage.0;
-                audio
-                    .play(
-                        [
-                            audio_assets.playerhit1.clone(),
-                            audio_assets.playerhit2.clone(),
-                            audio_assets.playerhit3.clone(),
-                            audio_assets.playerhit4.clone(),
-                            audio_assets.playerhit5.clone(),
-                        ]
-                        .choose(&mut rng.0)
-                        .unwrap()
-                        .clone(),
-                    )
-                    .with_playback_rate(1.8)
-                    .with_volume((audio_volumes.sfx * 0.8) as f64);
+    for hit in hits.iter() {
+        let damage = match hit.damage {
+            Some(damage) => damage,
+            None => continue,
+        };
+        // The logical player has the collider, the render player has the health
+        for (render_player, logical_player) in &player {
+            if hit.entity == logical_player.0 {
+                damage_events.send(DamageEvent {
+                    target: render_player,
+                    source: hit.source,
+                    amount: damage,
+                    kind: DamageKind::Projectile,
+                    point: hit.point,
+                    normal: hit.normal,
+                });
             }
         }
     }
@@ -0,0 +1,300 @@
+use std::f32::consts::TAU;
+
+use bevy::{math::vec3, prelude::*};
+use bevy_rapier3d::prelude::{QueryFilter, RapierContext};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{navmesh::NavMesh, GameRng, Health};
+
+use super::{kinds::UnitBehavior, UnitData, UnitsStates, HEAD_HEIGHT};
+
+/// How far units go to get around the side of their target
+const FLANK_DISTANCE: f32 = 8.0;
+/// How far units back off when retreating
+const RETREAT_DISTANCE: f32 = 6.0;
+/// How far units look for cover
+const COVER_SEARCH_DISTANCE: f32 = 5.0;
+const COVER_CANDIDATES: usize = 8;
+
+/// What a unit is trying to do. Each is scored for the unit's situation and weighted by its
+/// kind, the best one picks the animation state and where to go
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum UnitAction {
+    #[default]
+    Roam,
+    Attack,
+    /// Walks sideways while shooting
+    Strafe,
+    SeekCover,
+    Flank,
+    Retreat,
+    /// Goes to where the target was last seen
+    Investigate,
+}
+
+/// Per kind weights for each action, 0.0 disables it
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct UtilityWeights {
+    pub roam: f32,
+    pub attack: f32,
+    pub strafe: f32,
+    pub seek_cover: f32,
+    pub flank: f32,
+    pub retreat: f32,
+    pub investigate: f32,
+    /// Fraction of full health below which units retreat
+    pub retreat_health: f32,
+    /// Seconds the last known target position is remembered
+    pub memory: f32,
+}
+
+impl Default for UtilityWeights {
+    fn default() -> Self {
+        UtilityWeights {
+            roam: 1.0,
+            attack: 1.0,
+            strafe: 0.3,
+            seek_cover: 0.3,
+            flank: 0.2,
+            retreat: 0.5,
+            investigate: 0.8,
+            retreat_health: 0.3,
+            memory: 8.0,
+        }
+    }
+}
+
+impl UtilityWeights {
+    fn score(&self, action: UnitAction, unit: &UnitData, health: f32) -> f32 {
+        let has_target = unit.target_to_shoot.is_some();
+        let hurt = 1.0 - (health / unit.max_health).clamp(0.0, 1.0);
+        match action {
+            UnitAction::Roam if has_target || unit.last_known_target.is_some() => self.roam * 0.1,
+            UnitAction::Roam => self.roam,
+            UnitAction::Investigate if !has_target && unit.last_known_target.is_some() => {
+                self.investigate
+            }
+            UnitAction::Investigate => 0.0,
+            _ if !has_target => 0.0,
+            UnitAction::Attack => self.attack,
+            UnitAction::Strafe => self.strafe,
+            UnitAction::SeekCover => self.seek_cover * (1.0 + hurt),
+            UnitAction::Flank => self.flank,
+            UnitAction::Retreat if health / unit.max_health < self.retreat_health => {
+                self.retreat * 2.0
+            }
+            UnitAction::Retreat => 0.0,
+        }
+    }
+}
+
+/// Picks the next action when the current state runs out, or straight away when a target
+/// is spotted or lost
+pub fn decide(
+    fixed_time: Res<FixedTime>,
+    mut units: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut UnitData,
+        &UnitBehavior,
+        &Health,
+    )>,
+    rapier_context: Res<RapierContext>,
+    navmesh: Res<NavMesh>,
+    mut rng: ResMut<GameRng>,
+) {
+    let dt = fixed_time.period.as_secs_f32();
+    for (entity, trans, mut unit, behavior, health) in &mut units {
+        let weights = &behavior.utility;
+        let had_target = unit.had_target;
+        unit.had_target = unit.target_to_shoot.is_some();
+        if let Some(target) = unit.target_to_shoot {
+            unit.last_known_target = Some(target);
+            unit.memory_timer = weights.memory;
+        } else {
+            unit.memory_timer -= dt;
+            let arrived = unit.action == UnitAction::Investigate && unit.arrived;
+            if unit.memory_timer <= 0.0 || arrived {
+                unit.last_known_target = None;
+            }
+        }
+        if unit.init && had_target != unit.had_target {
+            unit.state_timer = 0.0;
+        }
+
+        unit.state_timer -= dt;
+        if unit.state_timer >= 0.0 {
+            continue;
+        }
+        let position = trans.translation();
+        if !unit.init {
+            // for some reason they disappear if they don't walk first
+            unit.init = true;
+            unit.action = UnitAction::Roam;
+            start_state(&mut unit, UnitsStates::Walk, position, &navmesh, &mut rng);
+            continue;
+        }
+
+        let mut best = (UnitAction::Roam, f32::MIN);
+        for action in [
+            UnitAction::Roam,
+            UnitAction::Attack,
+            UnitAction::Strafe,
+            UnitAction::SeekCover,
+            UnitAction::Flank,
+            UnitAction::Retreat,
+            UnitAction::Investigate,
+        ] {
+            let score = weights.score(action, &unit, health.0);
+            if score <= 0.0 {
+                continue;
+            }
+            // A little noise so units of the same kind don't act in lockstep
+            let score = score * rng.gen_range(0.75..1.25);
+            if score > best.1 {
+                best = (action, score);
+            }
+        }
+
+        let action = match best.0 {
+            UnitAction::SeekCover => {
+                match find_cover(entity, position, &unit, &rapier_context, &mut rng) {
+                    Some(cover) => {
+                        start_move(&mut unit, cover, position, &navmesh, &mut rng);
+                        UnitAction::SeekCover
+                    }
+                    None => UnitAction::Attack,
+                }
+            }
+            action => action,
+        };
+        unit.action = action;
+        let target = unit.target_to_shoot.unwrap_or(position);
+        let to_target = (target - position) * vec3(1.0, 0.0, 1.0);
+        let away = -to_target.normalize_or_zero();
+        let side = Vec3::Y.cross(away);
+        match action {
+            UnitAction::Roam => {
+                let state = UnitsStates::rng_pick(&mut rng, &behavior.roam);
+                start_state(&mut unit, state, position, &navmesh, &mut rng);
+            }
+            UnitAction::Attack => {
+                let state = UnitsStates::rng_pick(&mut rng, &behavior.attacking);
+                start_state(&mut unit, state, position, &navmesh, &mut rng);
+            }
+            UnitAction::Strafe => {
+                let sign = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+                let dest = position + side * sign * rng.gen_range(2.0..4.0);
+                start_move(&mut unit, dest, position, &navmesh, &mut rng);
+            }
+            UnitAction::SeekCover => (),
+            UnitAction::Flank => {
+                let sign = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+                let dest = target + side * sign * FLANK_DISTANCE;
+                start_move(&mut unit, dest, position, &navmesh, &mut rng);
+                unit.state_timer = rng.gen_range(2.0..4.0);
+            }
+            UnitAction::Retreat => {
+                let dest = position + away * RETREAT_DISTANCE;
+                start_move(&mut unit, dest, position, &navmesh, &mut rng);
+                unit.state_timer = rng.gen_range(2.0..3.0);
+            }
+            UnitAction::Investigate => {
+                let dest = unit.last_known_target.unwrap_or(unit.spawn);
+                start_move(&mut unit, dest, position, &navmesh, &mut rng);
+                unit.state_timer = rng.gen_range(2.0..4.0);
+            }
+        }
+    }
+}
+
+/// Sets the state's animation, how long it lasts, and where roaming units walk to
+fn start_state(
+    unit: &mut UnitData,
+    state: UnitsStates,
+    position: Vec3,
+    navmesh: &NavMesh,
+    rng: &mut GameRng,
+) {
+    unit.current_state = state;
+    match state {
+        UnitsStates::Walk | UnitsStates::WalkLazy => {
+            unit.state_timer = rng.gen_range(1.0..2.0);
+            let r = unit.max_radius;
+            let dest = vec3(
+                unit.spawn.x + rng.gen_range(-r..r),
+                unit.spawn.y,
+                unit.spawn.z + rng.gen_range(-r..r),
+            );
+            unit.walk_to(position, dest, navmesh);
+        }
+        UnitsStates::Bonk => {
+            unit.state_timer = rng.gen_range(2.0..3.0);
+            unit.arrived = true;
+        }
+        UnitsStates::Idle | UnitsStates::Bob | UnitsStates::Fire | UnitsStates::Stop => {
+            unit.state_timer = rng.gen_range(1.0..2.0);
+            unit.arrived = true;
+        }
+    }
+}
+
+fn start_move(
+    unit: &mut UnitData,
+    dest: Vec3,
+    position: Vec3,
+    navmesh: &NavMesh,
+    rng: &mut GameRng,
+) {
+    unit.current_state = UnitsStates::Walk;
+    unit.state_timer = rng.gen_range(1.0..2.0);
+    unit.walk_to(position, dest, navmesh);
+}
+
+/// A nearby spot the unit can walk to that its target can't see
+fn find_cover(
+    entity: Entity,
+    position: Vec3,
+    unit: &UnitData,
+    rapier_context: &RapierContext,
+    rng: &mut GameRng,
+) -> Option<Vec3> {
+    let target = unit.target_to_shoot?;
+    let filter = QueryFilter::default()
+        .exclude_collider(entity)
+        .exclude_sensors();
+    let offset = rng.gen_range(0.0..TAU);
+    (0..COVER_CANDIDATES).find_map(|i| {
+        let angle = offset + TAU * i as f32 / COVER_CANDIDATES as f32;
+        let candidate = position + vec3(angle.cos(), 0.0, angle.sin()) * COVER_SEARCH_DISTANCE;
+        let head = candidate + Vec3::Y * HEAD_HEIGHT;
+        // The way there has to be clear
+        let to_candidate = candidate - position;
+        let blocked_path = rapier_context
+            .cast_ray(
+                position + Vec3::Y * 0.5,
+                to_candidate.normalize(),
+                to_candidate.length(),
+                false,
+                filter,
+            )
+            .is_some();
+        if blocked_path {
+            return None;
+        }
+        // And something has to be between it and the target
+        let to_target = target - head;
+        let hidden = rapier_context
+            .cast_ray(
+                head,
+                to_target.normalize(),
+                to_target.length() - 1.0,
+                false,
+                filter,
+            )
+            .is_some();
+        hidden.then_some(candidate)
+    })
+}
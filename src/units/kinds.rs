@@ -0,0 +1,181 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    gltf::GltfExtras,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{assets::UnitKindAssets, damage::Armor, GameRng};
+
+use super::{ai::UtilityWeights, perception::Perception, UnitsStates};
+
+/// Paths of a unit's animation clips, one per state that has an animation
+#[derive(Deserialize, Clone, Debug)]
+pub struct UnitAnimationPaths {
+    pub walk: String,
+    pub idle: String,
+    pub bob: String,
+    pub bonk: String,
+    pub fire: String,
+    pub walk_lazy: String,
+}
+
+#[derive(Component, Clone)]
+pub struct UnitAnimations {
+    pub walk: Handle<AnimationClip>,
+    pub idle: Handle<AnimationClip>,
+    pub bob: Handle<AnimationClip>,
+    pub bonk: Handle<AnimationClip>,
+    pub fire: Handle<AnimationClip>,
+    pub walk_lazy: Handle<AnimationClip>,
+}
+
+impl UnitAnimationPaths {
+    pub fn load(&self, asset_server: &AssetServer) -> UnitAnimations {
+        UnitAnimations {
+            walk: asset_server.load(&self.walk),
+            idle: asset_server.load(&self.idle),
+            bob: asset_server.load(&self.bob),
+            bonk: asset_server.load(&self.bonk),
+            fire: asset_server.load(&self.fire),
+            walk_lazy: asset_server.load(&self.walk_lazy),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct UnitWeapon {
+    /// Targets further than this are ignored
+    pub range: f32,
+    /// Shots per second while firing
+    pub fire_rate: f32,
+    pub projectile_speed: f32,
+    /// Multiplies the damage per shot for the difficulty
+    pub damage: f32,
+}
+
+/// Weights for picking the next action and state, the higher the more likely
+#[derive(Component, Deserialize, Clone, Debug)]
+pub struct UnitBehavior {
+    /// When there's nothing to shoot at
+    pub roam: Vec<(UnitsStates, u32)>,
+    /// When a target is in sight
+    pub attacking: Vec<(UnitsStates, u32)>,
+    #[serde(default)]
+    pub utility: UtilityWeights,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UnitKind {
+    pub name: String,
+    pub scene: String,
+    pub animations: UnitAnimationPaths,
+    pub health: f32,
+    /// Each unit gets a random speed in this range, scales walking and some animations
+    pub speed: (f32, f32),
+    /// How far from its spawn point a unit walks
+    pub roam_radius: f32,
+    pub weapon: UnitWeapon,
+    #[serde(default)]
+    pub perception: Perception,
+    #[serde(default)]
+    pub armor: Armor,
+    pub behavior: UnitBehavior,
+}
+
+impl UnitKind {
+    pub fn random_speed(&self, rng: &mut GameRng) -> f32 {
+        let (min, max) = self.speed;
+        if min < max {
+            rng.gen_range(min..max)
+        } else {
+            min
+        }
+    }
+}
+
+#[derive(Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "0b8d5f5e-3c1f-4d7a-a7e2-8f6f3d2b9c41"]
+pub struct UnitKindManifest {
+    /// Used by spawns that don't name a kind
+    pub default: String,
+    pub kinds: Vec<UnitKind>,
+}
+
+impl UnitKindManifest {
+    pub fn get(&self, name: &str) -> Option<&UnitKind> {
+        self.kinds.iter().find(|kind| kind.name == name)
+    }
+}
+
+#[derive(Default)]
+pub struct UnitKindManifestLoader;
+
+impl AssetLoader for UnitKindManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest = serde_json::from_slice::<UnitKindManifest>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["units.json"]
+    }
+}
+
+#[derive(Deserialize)]
+struct SpawnExtras {
+    kind: Option<String>,
+}
+
+/// The kind named by a spawn node, from a `kind` extra or a name like `enemyspawn.sniper.001`
+pub fn spawn_kind_name(name: &str, extras: Option<&GltfExtras>) -> Option<String> {
+    extras
+        .and_then(|extras| serde_json::from_str::<SpawnExtras>(&extras.value).ok())
+        .and_then(|extras| extras.kind)
+        .or_else(|| {
+            // Blender adds numbered suffixes to duplicated nodes
+            name.split('.')
+                .skip(1)
+                .find(|part| !part.chars().all(|c| c.is_ascii_digit()))
+                .map(str::to_owned)
+        })
+        .map(|kind| kind.to_lowercase())
+}
+
+/// Looks up unit kinds from the loaded unit kind manifest
+#[derive(SystemParam)]
+pub struct UnitKinds<'w> {
+    unit_kind_assets: Res<'w, UnitKindAssets>,
+    manifests: Res<'w, Assets<UnitKindManifest>>,
+}
+
+impl<'w> UnitKinds<'w> {
+    pub fn manifest(&self) -> &UnitKindManifest {
+        self.manifests
+            .get(&self.unit_kind_assets.manifest)
+            .expect("unit kind manifest should be loaded")
+    }
+
+    /// Falls back to the default kind if there's no kind with this name
+    pub fn get_or_default(&self, name: Option<&str>) -> Option<&UnitKind> {
+        let manifest = self.manifest();
+        if let Some(name) = name {
+            match manifest.get(name) {
+                Some(kind) => return Some(kind),
+                None => warn!("Unknown unit kind {name}, using {}", manifest.default),
+            }
+        }
+        manifest.get(&manifest.default)
+    }
+}
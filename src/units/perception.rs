@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::{QueryFilter, RapierContext};
+use serde::Deserialize;
+
+use crate::{
+    character_controller::{LogicalPlayerEntity, ShootableByUnit},
+    damage::{DamageEvent, Damaged},
+    player::PlayerFired,
+};
+
+use super::{horizontal, kinds::UnitBehavior, UnitData, HEAD_HEIGHT};
+
+/// How a unit notices targets, per kind
+#[derive(Component, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct Perception {
+    /// Degrees across the view cone, targets outside it aren't seen until the unit is engaged
+    pub fov: f32,
+    /// Seconds a target has to be in view before an unsuspecting unit reacts
+    pub reaction_time: f32,
+    /// Gunfire closer than this is heard, through walls
+    pub hearing_radius: f32,
+    /// Units closer than this are alerted when this unit spots a target
+    pub alert_radius: f32,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Perception {
+            fov: 120.0,
+            reaction_time: 0.5,
+            hearing_radius: 30.0,
+            alert_radius: 15.0,
+        }
+    }
+}
+
+impl Perception {
+    fn in_view(&self, forward: Vec3, to_target: Vec3) -> bool {
+        let forward = horizontal(forward);
+        let to_target = horizontal(to_target);
+        if forward == Vec3::ZERO || to_target == Vec3::ZERO {
+            return true;
+        }
+        forward.angle_between(to_target).to_degrees() <= self.fov * 0.5
+    }
+}
+
+/// Seconds the unit's current target has been in view, builds up to the reaction time and
+/// drains again while it's hidden
+#[derive(Component, Default)]
+pub struct Awareness {
+    pub spotting: f32,
+}
+
+/// Queued by a unit when it spots a target
+pub struct UnitAlert {
+    pub source: Entity,
+    /// Where the unit that spotted the target is
+    pub origin: Vec3,
+    /// Where the target was spotted
+    pub position: Vec3,
+    pub radius: f32,
+}
+
+/// What units hear on the next fixed step. Gunfire and hits are events that are only kept for
+/// two frames, and at high frame rates there are frames without a fixed step
+#[derive(Resource, Default)]
+pub struct Heard {
+    gunfire: Vec<Vec3>,
+    hits: Vec<DamageEvent>,
+    alerts: Vec<UnitAlert>,
+}
+
+/// Queues this frame's gunfire and hits for the next fixed step
+pub fn queue_heard(
+    mut heard: ResMut<Heard>,
+    mut player_fired: EventReader<PlayerFired>,
+    mut damaged_events: EventReader<Damaged>,
+) {
+    heard
+        .gunfire
+        .extend(player_fired.iter().map(|fired| fired.position));
+    heard
+        .hits
+        .extend(damaged_events.iter().map(|Damaged(damage)| damage.clone()));
+}
+
+/// Targets the nearest shootable in range and in sight. Unengaged units only see inside their
+/// view cone and take a moment to react, unless something already made them suspicious
+pub fn see(
+    fixed_time: Res<FixedTime>,
+    mut unit_entities: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut UnitData,
+        &Perception,
+        &mut Awareness,
+    )>,
+    shootables: Query<
+        (Entity, &GlobalTransform, Option<&LogicalPlayerEntity>),
+        With<ShootableByUnit>,
+    >,
+    rapier_context: Res<RapierContext>,
+    mut heard: ResMut<Heard>,
+) {
+    let dt = fixed_time.period.as_secs_f32();
+    for (unit_entity, unit_trans, mut unit, perception, mut awareness) in &mut unit_entities {
+        let unit_trans = unit_trans.compute_transform();
+        let mut closest_entity = None;
+        let mut closest_pos = Vec3::splat(f32::INFINITY);
+        let mut closest_dist = f32::INFINITY;
+        let mut logical_player = None;
+        for (shootable, shootable_trans, logical_player_shootable) in &shootables {
+            let shootable_trans = shootable_trans.compute_transform();
+            let this_dist = shootable_trans.translation.distance(unit_trans.translation);
+            if this_dist < closest_dist {
+                closest_dist = this_dist;
+                closest_pos = shootable_trans.translation;
+                closest_entity = Some(shootable);
+                logical_player = logical_player_shootable;
+            }
+        }
+
+        let engaged = unit.target_to_shoot.is_some();
+        let suspicious = unit.last_known_target.is_some();
+        unit.target_to_shoot = None;
+        unit.target_to_apply_damage = None;
+        let mut visible = None;
+        if let Some(closest_entity) = closest_entity {
+            let origin = unit_trans.translation + Vec3::Y * HEAD_HEIGHT;
+            let in_view = engaged || perception.in_view(unit_trans.forward(), closest_pos - origin);
+            if closest_dist < unit.weapon.range && in_view {
+                let hit = rapier_context.cast_ray(
+                    origin,
+                    (closest_pos - origin).normalize(),
+                    f32::MAX,
+                    false,
+                    QueryFilter::default()
+                        .exclude_collider(unit_entity)
+                        .exclude_sensors(),
+                );
+                if let Some(hit) = hit {
+                    let hit_player = logical_player.map_or(false, |player| hit.0 == player.0);
+                    if hit.0 == closest_entity || hit_player {
+                        visible = Some(closest_entity);
+                    }
+                }
+            }
+        }
+
+        match visible {
+            Some(target) => {
+                awareness.spotting += dt;
+                if engaged || suspicious || awareness.spotting >= perception.reaction_time {
+                    unit.target_to_shoot = Some(closest_pos);
+                    unit.target_to_apply_damage = Some(target);
+                    if !engaged {
+                        heard.alerts.push(UnitAlert {
+                            source: unit_entity,
+                            origin: unit_trans.translation,
+                            position: closest_pos,
+                            radius: perception.alert_radius,
+                        });
+                    }
+                }
+            }
+            None => awareness.spotting = (awareness.spotting - dt).max(0.0),
+        }
+    }
+}
+
+/// Makes units suspicious of gunfire they can hear, of targets other units spotted nearby and
+/// of whoever shot them, so they go and look
+pub fn hear(
+    mut units: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut UnitData,
+        &Perception,
+        &UnitBehavior,
+    )>,
+    sources: Query<&GlobalTransform>,
+    mut heard: ResMut<Heard>,
+) {
+    for damage in heard.hits.drain(..) {
+        if let Ok((_, _, mut unit, _, behavior)) = units.get_mut(damage.target) {
+            let position = match damage.source.and_then(|source| sources.get(source).ok()) {
+                Some(source) => source.translation(),
+                None => damage.point,
+            };
+            alert(&mut unit, behavior, position);
+        }
+    }
+    for position in heard.gunfire.drain(..) {
+        for (_, trans, mut unit, perception, behavior) in &mut units {
+            if trans.translation().distance(position) < perception.hearing_radius {
+                alert(&mut unit, behavior, position);
+            }
+        }
+    }
+    for unit_alert in heard.alerts.drain(..) {
+        for (entity, trans, mut unit, _, behavior) in &mut units {
+            if entity != unit_alert.source
+                && trans.translation().distance(unit_alert.origin) < unit_alert.radius
+            {
+                alert(&mut unit, behavior, unit_alert.position);
+            }
+        }
+    }
+}
+
+fn alert(unit: &mut UnitData, behavior: &UnitBehavior, position: Vec3) {
+    if unit.target_to_shoot.is_some() {
+        return;
+    }
+    if unit.last_known_target.is_none() {
+        // Decide what to do about it now
+        unit.state_timer = 0.0;
+    }
+    unit.last_known_target = Some(position);
+    unit.memory_timer = behavior.utility.memory;
+}
@@ -0,0 +1,169 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    assets::{PropAssets, WeaponAssets},
+    sfx::Sfx,
+};
+
+/// Which projectile prop a weapon's shots show
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProjectileProp {
+    Full,
+    Lite,
+    LiteRed,
+}
+
+impl ProjectileProp {
+    pub fn scene(&self, props: &PropAssets) -> Handle<Scene> {
+        match self {
+            ProjectileProp::Full => props.projectile.clone(),
+            ProjectileProp::Lite => props.projectile_lite.clone(),
+            ProjectileProp::LiteRed => props.projectile_lite_red.clone(),
+        }
+    }
+}
+
+/// The gun model in front of the camera
+#[derive(Deserialize, Clone, Debug)]
+pub struct Viewmodel {
+    pub scene: String,
+    /// Drawn with the gun, but without its env settings
+    #[serde(default)]
+    pub emit_scene: Option<String>,
+    pub flash_scene: String,
+    /// From the camera
+    pub offset: Vec3,
+    /// From the camera
+    pub flash_offset: Vec3,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WeaponDef {
+    pub name: String,
+    /// Per hit, before falloff
+    pub damage: f32,
+    /// Shots per second while fire is held
+    pub fire_rate: f32,
+    /// Degrees across the cone shots land in, when standing still and not firing
+    pub spread: f32,
+    /// Degrees each shot adds to the spread
+    pub bloom: f32,
+    /// Most degrees sustained fire adds to the spread
+    pub max_bloom: f32,
+    /// Degrees of bloom recovered per second
+    pub bloom_recovery: f32,
+    /// Degrees added to the spread per m/s the player moves
+    pub move_spread: f32,
+    /// Degrees the view kicks up per shot, and at most sideways either way
+    pub recoil: Vec2,
+    /// Damage multipliers at distances, linear in between and clamped at the ends
+    pub falloff: Vec<(f32, f32)>,
+    /// Rounds per magazine
+    pub magazine: u32,
+    /// Most rounds carried besides the loaded ones, the player starts with this many
+    pub max_reserve: u32,
+    /// Seconds
+    pub reload_time: f32,
+    pub projectile: ProjectileProp,
+    pub projectile_speed: f32,
+    /// Where projectiles start, from the camera
+    pub muzzle_offset: Vec3,
+    pub sound: Sfx,
+    pub viewmodel: Viewmodel,
+}
+
+impl WeaponDef {
+    /// Degrees across the cone shots land in, after `bloom` degrees of sustained fire and
+    /// moving at `speed`
+    pub fn spread_at(&self, bloom: f32, speed: f32) -> f32 {
+        self.spread + bloom.clamp(0.0, self.max_bloom) + self.move_spread * speed
+    }
+
+    pub fn falloff(&self, distance: f32) -> f32 {
+        let points = &self.falloff;
+        match (points.first(), points.last()) {
+            (Some(first), _) if distance <= first.0 => first.1,
+            (_, Some(last)) if distance >= last.0 => last.1,
+            (Some(_), Some(_)) => {
+                let i = points.partition_point(|point| point.0 < distance);
+                let (d0, m0) = points[i - 1];
+                let (d1, m1) = points[i];
+                m0 + (m1 - m0) * (distance - d0) / (d1 - d0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "6c3b1f0e-9d2a-4e57-b8c4-1a7e5f3d9b20"]
+pub struct WeaponManifest {
+    pub weapons: Vec<WeaponDef>,
+    /// Names of the weapons the player carries, in number key order
+    pub loadout: Vec<String>,
+}
+
+impl WeaponManifest {
+    pub fn get(&self, name: &str) -> Option<&WeaponDef> {
+        self.weapons.iter().find(|weapon| weapon.name == name)
+    }
+}
+
+#[derive(Default)]
+pub struct WeaponManifestLoader;
+
+impl AssetLoader for WeaponManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest = serde_json::from_slice::<WeaponManifest>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapons.json"]
+    }
+}
+
+/// Looks up weapons from the loaded weapon manifest
+#[derive(SystemParam)]
+pub struct Weapons<'w> {
+    weapon_assets: Res<'w, WeaponAssets>,
+    manifests: Res<'w, Assets<WeaponManifest>>,
+}
+
+impl<'w> Weapons<'w> {
+    pub fn manifest(&self) -> &WeaponManifest {
+        self.manifests
+            .get(&self.weapon_assets.manifest)
+            .expect("weapon manifest should be loaded")
+    }
+
+    /// The player's weapons, skipping names that aren't defined
+    pub fn loadout(&self) -> Vec<WeaponDef> {
+        let manifest = self.manifest();
+        manifest
+            .loadout
+            .iter()
+            .filter_map(|name| {
+                let weapon = manifest.get(name);
+                if weapon.is_none() {
+                    warn!("Unknown weapon {name} in loadout");
+                }
+                weapon.cloned()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,445 @@
+//! Runs the gameplay plugins without a window, renderer or audio. Frames are stepped by hand
+//! at the fixed timestep, so a test plays out the same way every time.
+
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::CommandQueue,
+    hierarchy::HierarchyPlugin,
+    input::{keyboard::KeyboardInput, ButtonState, InputPlugin},
+    prelude::*,
+    scene::ScenePlugin,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+    utils::Instant,
+};
+use bevy_fps_controller::controller::{
+    FpsController, FpsControllerInput, LogicalPlayer, RenderPlayer,
+};
+use bevy_rapier3d::prelude::*;
+use traverse::{
+    assets::{LevelAssets, PropAssets, UnitKindAssets, WeaponAssets},
+    character_controller::{spawn_player, GRAVITY},
+    damage::{DamageEvent, DamageKind},
+    levels::{
+        manifest::{LevelManifest, LevelProperties},
+        GameLevel,
+    },
+    pickups::{Pickup, PickupSpawns},
+    player::{PlayerFired, PlayerGun, Projectile, RunStats, TeleportPlayer},
+    settings::Settings,
+    units::{
+        ai::UnitAction, kinds::UnitKindManifest, DamagePlayer, Difficulty, EnemySpawns, UnitData,
+    },
+    weapons::WeaponManifest,
+    AppState, GameLoading, GameplayPlugin, Health, TIMESTEP,
+};
+
+pub struct Sim {
+    pub app: App,
+    manifest: LevelManifest,
+    clock: Instant,
+}
+
+impl Sim {
+    /// In game in the kitchen, standing on a flat floor
+    pub fn start() -> Sim {
+        let manifest: LevelManifest =
+            serde_json::from_str(include_str!("../../assets/levels/manifest.levels.json"))
+                .expect("level manifest should parse");
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(ScenePlugin)
+            .add_asset::<Mesh>()
+            .insert_resource(Settings::default())
+            .add_plugin(GameplayPlugin);
+
+        let manifest_handle = app
+            .world
+            .resource_mut::<Assets<LevelManifest>>()
+            .add(manifest.clone());
+        let unit_kinds: UnitKindManifest =
+            serde_json::from_str(include_str!("../../assets/units/kinds.units.json"))
+                .expect("unit kinds should parse");
+        let unit_kinds_handle = app
+            .world
+            .resource_mut::<Assets<UnitKindManifest>>()
+            .add(unit_kinds);
+        let weapons: WeaponManifest =
+            serde_json::from_str(include_str!("../../assets/weapons/manifest.weapons.json"))
+                .expect("weapons should parse");
+        let weapons_handle = app
+            .world
+            .resource_mut::<Assets<WeaponManifest>>()
+            .add(weapons);
+        app.insert_resource(LevelAssets {
+            manifest: manifest_handle,
+        })
+        .insert_resource(UnitKindAssets {
+            manifest: unit_kinds_handle,
+        })
+        .insert_resource(WeaponAssets {
+            manifest: weapons_handle,
+        })
+        .insert_resource(PropAssets::default());
+        app.world
+            .resource_mut::<NextState<GameLoading>>()
+            .set(GameLoading::Loaded);
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+
+        app.world.spawn((
+            Collider::cuboid(100.0, 0.5, 100.0),
+            RigidBody::Fixed,
+            TransformBundle::from_transform(Transform::from_xyz(0.0, -0.5, 0.0)),
+        ));
+
+        let settings = app.world.resource::<Settings>().clone();
+        let mut queue = CommandQueue::default();
+        spawn_player(&mut Commands::new(&mut queue, &app.world), &settings);
+        queue.apply(&mut app.world);
+        for mut fps_controller in app
+            .world
+            .query::<&mut FpsController>()
+            .iter_mut(&mut app.world)
+        {
+            // As if the cursor was locked, otherwise the game is paused
+            fps_controller.enable_input = true;
+            fps_controller.gravity = GRAVITY;
+        }
+
+        let mut sim = Sim {
+            app,
+            manifest,
+            clock: Instant::now(),
+        };
+        sim.step(1);
+        sim
+    }
+
+    pub fn step(&mut self, frames: u32) {
+        self.step_frames(frames, 1);
+    }
+
+    /// Steps frames `frames_per_step` times shorter than the fixed timestep, like a high
+    /// refresh rate display, so most frames don't run the fixed schedule
+    pub fn step_frames(&mut self, frames: u32, frames_per_step: u32) {
+        for _ in 0..frames {
+            self.clock += Duration::from_secs_f32(TIMESTEP / frames_per_step as f32);
+            self.app
+                .insert_resource(TimeUpdateStrategy::ManualInstant(self.clock));
+            self.app.update();
+        }
+    }
+
+    /// Steps until `done` or `seconds` have passed, returns how long it took
+    pub fn run_until(&mut self, seconds: f32, done: impl Fn(&mut Sim) -> bool) -> Option<f32> {
+        let frames = (seconds / TIMESTEP).ceil() as u32;
+        for frame in 1..=frames {
+            self.step(1);
+            if done(self) {
+                return Some(frame as f32 * TIMESTEP);
+            }
+        }
+        None
+    }
+
+    pub fn level(&self, level: &GameLevel) -> &LevelProperties {
+        self.manifest
+            .get(level)
+            .expect("level should be in manifest")
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.app.insert_resource(difficulty);
+    }
+
+    /// Units are spawned from enemyspawn nodes like the ones in level scenes
+    pub fn spawn_units(&mut self, positions: &[Vec3]) {
+        let spawns: Vec<_> = positions
+            .iter()
+            .map(|position| ("enemyspawn", *position))
+            .collect();
+        self.spawn_named_units(&spawns);
+    }
+
+    /// The node names pick the unit kinds, like `enemyspawn.sniper`
+    pub fn spawn_named_units(&mut self, spawns: &[(&str, Vec3)]) {
+        self.app
+            .world
+            .spawn((TransformBundle::default(), EnemySpawns))
+            .with_children(|parent| {
+                for (name, position) in spawns {
+                    parent.spawn((
+                        Name::new(name.to_string()),
+                        TransformBundle::from_transform(Transform::from_translation(*position)),
+                    ));
+                }
+            });
+    }
+
+    pub fn spawn_wall(&mut self, position: Vec3, half_extents: Vec3) {
+        self.app.world.spawn((
+            Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            RigidBody::Fixed,
+            TransformBundle::from_transform(Transform::from_translation(position)),
+        ));
+    }
+
+    /// A projectile like the ones units fire, without a scene
+    pub fn spawn_projectile(&mut self, from: Vec3, to: Vec3, speed: f32, damage: f32) {
+        self.app.world.spawn((
+            TransformBundle::from_transform(
+                Transform::from_translation(from).looking_at(to, Vec3::Y),
+            ),
+            Projectile {
+                speed,
+                max_dist: 1000.0,
+                dist_trav: 0.0,
+                shooter: None,
+            },
+            DamagePlayer(damage),
+        ));
+    }
+
+    pub fn projectile_count(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<Projectile>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    pub fn teleport(&mut self, level: GameLevel) {
+        self.app
+            .world
+            .resource_mut::<Events<TeleportPlayer>>()
+            .send(TeleportPlayer(level));
+    }
+
+    pub fn current_level(&self) -> GameLevel {
+        self.app.world.resource::<State<GameLevel>>().0.clone()
+    }
+
+    pub fn unit_count(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<UnitData>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    pub fn units(&mut self) -> Vec<UnitData> {
+        self.app
+            .world
+            .query::<&UnitData>()
+            .iter(&self.app.world)
+            .cloned()
+            .collect()
+    }
+
+    /// The unit that spawned nearest to `spawn`
+    pub fn unit_spawned_at(&mut self, spawn: Vec3) -> UnitData {
+        self.units()
+            .into_iter()
+            .min_by(|a, b| a.spawn.distance(spawn).total_cmp(&b.spawn.distance(spawn)))
+            .expect("there should be a unit")
+    }
+
+    /// Sends a DamageEvent to every unit, as if the player shot them all
+    pub fn damage_units(&mut self, amount: f32, kind: DamageKind) {
+        let units: Vec<_> = self
+            .app
+            .world
+            .query_filtered::<(Entity, &Transform), With<UnitData>>()
+            .iter(&self.app.world)
+            .map(|(entity, transform)| (entity, transform.translation))
+            .collect();
+        let mut events = self.app.world.resource_mut::<Events<DamageEvent>>();
+        for (target, point) in units {
+            events.send(DamageEvent {
+                target,
+                source: None,
+                amount,
+                kind,
+                point,
+                normal: Vec3::Y,
+            });
+        }
+    }
+
+    pub fn unit_positions(&mut self) -> Vec<Vec3> {
+        self.app
+            .world
+            .query_filtered::<&Transform, With<UnitData>>()
+            .iter(&self.app.world)
+            .map(|transform| transform.translation)
+            .collect()
+    }
+
+    pub fn unit_healths(&mut self) -> Vec<f32> {
+        self.app
+            .world
+            .query_filtered::<&Health, With<UnitData>>()
+            .iter(&self.app.world)
+            .map(|health| health.0)
+            .collect()
+    }
+
+    /// Sets the health of the unit that spawned nearest to `spawn`, as a fraction of its full
+    /// health, without it knowing who did it
+    pub fn set_unit_health(&mut self, spawn: Vec3, fraction: f32) {
+        let mut units = self.app.world.query::<(&UnitData, &mut Health)>();
+        let (unit, mut health) = units
+            .iter_mut(&mut self.app.world)
+            .min_by(|(a, _), (b, _)| a.spawn.distance(spawn).total_cmp(&b.spawn.distance(spawn)))
+            .expect("there should be a unit");
+        health.0 = unit.max_health * fraction;
+    }
+
+    /// Steps for `seconds`, returns the actions the units spawned nearest to each of `spawns`
+    /// picked, in order
+    pub fn unit_actions(&mut self, seconds: f32, spawns: &[Vec3]) -> Vec<Vec<UnitAction>> {
+        let mut actions = vec![Vec::new(); spawns.len()];
+        let frames = (seconds / TIMESTEP).ceil() as u32;
+        for _ in 0..frames {
+            self.step(1);
+            for (spawn, actions) in spawns.iter().zip(&mut actions) {
+                let action = self.unit_spawned_at(*spawn).action;
+                if actions.last() != Some(&action) {
+                    actions.push(action);
+                }
+            }
+        }
+        actions
+    }
+
+    pub fn player_health(&mut self) -> f32 {
+        self.app
+            .world
+            .query_filtered::<&Health, With<RenderPlayer>>()
+            .single(&self.app.world)
+            .0
+    }
+
+    pub fn set_player_health(&mut self, health: f32) {
+        self.app
+            .world
+            .query_filtered::<&mut Health, With<RenderPlayer>>()
+            .single_mut(&mut self.app.world)
+            .0 = health;
+    }
+
+    pub fn player_position(&mut self) -> Vec3 {
+        self.app
+            .world
+            .query_filtered::<&Transform, With<LogicalPlayer>>()
+            .single(&self.app.world)
+            .translation
+    }
+
+    pub fn set_player_position(&mut self, position: Vec3) {
+        self.app
+            .world
+            .query_filtered::<&mut Transform, With<LogicalPlayer>>()
+            .single_mut(&mut self.app.world)
+            .translation = position;
+    }
+
+    /// As if the player shot, without needing input or a gun
+    pub fn player_fired(&mut self) {
+        let position = self.player_position();
+        self.app
+            .world
+            .resource_mut::<Events<PlayerFired>>()
+            .send(PlayerFired { position });
+    }
+
+    /// Pressed for one frame, through input events like a real keyboard
+    pub fn press_key(&mut self, key: KeyCode) {
+        let mut events = self.app.world.resource_mut::<Events<KeyboardInput>>();
+        events.send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state: ButtonState::Pressed,
+        });
+        self.step(1);
+        let mut events = self.app.world.resource_mut::<Events<KeyboardInput>>();
+        events.send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state: ButtonState::Released,
+        });
+    }
+
+    /// Pitch and yaw of the player's view, in radians
+    pub fn view_angles(&mut self) -> Vec2 {
+        let input = self
+            .app
+            .world
+            .query::<&FpsControllerInput>()
+            .single(&self.app.world);
+        Vec2::new(input.pitch, input.yaw)
+    }
+
+    pub fn weapon_name(&mut self) -> String {
+        self.app
+            .world
+            .query::<&PlayerGun>()
+            .single(&self.app.world)
+            .weapon()
+            .expect("player should have a weapon")
+            .name
+            .clone()
+    }
+
+    /// Rounds loaded and in reserve for the current weapon
+    pub fn ammo(&mut self) -> (u32, u32) {
+        let gun = self.app.world.query::<&PlayerGun>().single(&self.app.world);
+        let carried = gun.carried().expect("player should have a weapon");
+        (carried.magazine, carried.reserve)
+    }
+
+    pub fn set_ammo(&mut self, magazine: u32, reserve: u32) {
+        let mut gun = self
+            .app
+            .world
+            .query::<&mut PlayerGun>()
+            .single_mut(&mut self.app.world);
+        let current = gun.current;
+        let carried = &mut gun.weapons[current];
+        carried.magazine = magazine;
+        carried.reserve = reserve;
+    }
+
+    /// Pickups are spawned from pickup nodes like the ones in level scenes
+    pub fn spawn_pickup(&mut self, name: &str, position: Vec3) {
+        self.app
+            .world
+            .spawn((TransformBundle::default(), PickupSpawns))
+            .with_children(|parent| {
+                parent.spawn((
+                    Name::new(name.to_string()),
+                    TransformBundle::from_transform(Transform::from_translation(position)),
+                ));
+            });
+    }
+
+    pub fn pickup_count(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<Pickup>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    pub fn deaths(&self) -> u32 {
+        self.app.world.resource::<RunStats>().deaths
+    }
+}
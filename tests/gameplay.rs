@@ -0,0 +1,321 @@
+mod common;
+
+use bevy::{
+    math::{vec3, Vec3Swizzles},
+    prelude::KeyCode,
+};
+use common::Sim;
+use traverse::{
+    damage::DamageKind,
+    levels::GameLevel,
+    units::{ai::UnitAction, Difficulty},
+};
+
+#[test]
+fn drones_kill_the_player_on_ultra() {
+    let mut sim = Sim::start();
+    sim.set_difficulty(Difficulty::Ultra);
+    sim.spawn_units(&[
+        vec3(8.0, 0.0, 0.0),
+        vec3(-8.0, 0.0, 0.0),
+        vec3(0.0, 0.0, 8.0),
+        vec3(0.0, 0.0, -8.0),
+    ]);
+    let died_after = sim.run_until(30.0, |sim| sim.deaths() > 0);
+    assert!(died_after.is_some(), "player survived 30s of four drones");
+}
+
+#[test]
+fn walls_block_drone_fire() {
+    let mut sim = Sim::start();
+    sim.set_difficulty(Difficulty::Ultra);
+    sim.spawn_units(&[vec3(0.0, 0.0, 10.0)]);
+    sim.spawn_wall(vec3(0.0, 2.0, 5.0), vec3(5.0, 2.0, 0.2));
+    sim.step(600);
+    assert_eq!(sim.unit_count(), 1);
+    assert_eq!(sim.player_health(), 1.0);
+}
+
+#[test]
+fn teleport_resets_health_and_clears_drones() {
+    let mut sim = Sim::start();
+    sim.spawn_units(&[vec3(8.0, 0.0, 0.0), vec3(-8.0, 0.0, 0.0)]);
+    sim.step(2);
+    assert_eq!(sim.unit_count(), 2);
+    sim.set_player_health(0.25);
+
+    sim.teleport(GameLevel::BF1);
+    sim.step(2);
+    assert_eq!(sim.current_level(), GameLevel::BF1);
+    assert_eq!(sim.player_health(), 1.0);
+    assert_eq!(sim.unit_count(), 0);
+    let spawn_pos = sim.level(&GameLevel::BF1).spawn_pos;
+    assert!(sim.player_position().distance(spawn_pos) < 0.1);
+}
+
+#[test]
+fn falling_out_of_the_world_respawns() {
+    let mut sim = Sim::start();
+    sim.set_player_position(vec3(0.0, -600.0, 0.0));
+    sim.step(2);
+    assert_eq!(sim.deaths(), 1);
+    let spawn_pos = sim.level(&GameLevel::Kitchen).spawn_pos;
+    assert!(sim.player_position().distance(spawn_pos) < 0.1);
+}
+
+#[test]
+fn spawn_names_pick_unit_kinds() {
+    let mut sim = Sim::start();
+    sim.spawn_named_units(&[
+        ("enemyspawn.003", vec3(8.0, 0.0, 0.0)),
+        ("enemyspawn.heavy.001", vec3(-8.0, 0.0, 0.0)),
+        ("enemyspawn.nosuchkind", vec3(0.0, 0.0, 8.0)),
+    ]);
+    sim.step(2);
+    let mut healths = sim.unit_healths();
+    healths.sort_by(f32::total_cmp);
+    // Unknown kinds fall back to the default drone
+    assert_eq!(healths, vec![1.0, 1.0, 3.0]);
+}
+
+#[test]
+fn units_fall_to_the_floor_and_stay_out_of_walls() {
+    let mut sim = Sim::start();
+    sim.spawn_wall(vec3(0.0, 2.0, 6.0), vec3(5.0, 2.0, 0.2));
+    sim.spawn_units(&[vec3(0.0, 3.0, 7.0)]);
+    sim.step(600);
+    let position = sim.unit_positions()[0];
+    assert!(position.y.abs() < 0.1, "unit is floating at {position}");
+    assert!(position.z > 6.2, "unit went through the wall to {position}");
+}
+
+#[test]
+fn units_hear_gunfire_through_walls() {
+    let mut sim = Sim::start();
+    sim.spawn_wall(vec3(0.0, 2.0, 10.0), vec3(5.0, 2.0, 0.2));
+    let near = vec3(0.0, 0.0, 20.0);
+    let far = vec3(0.0, 0.0, -45.0);
+    sim.spawn_units(&[near, far]);
+    sim.step(2);
+    sim.player_fired();
+    sim.step(2);
+    assert!(sim.unit_spawned_at(near).last_known_target.is_some());
+    assert!(sim.unit_spawned_at(far).last_known_target.is_none());
+}
+
+#[test]
+fn units_hear_gunfire_and_hits_between_fixed_steps() {
+    let mut sim = Sim::start();
+    // Behind the player, so they can't see who's shooting
+    let near = vec3(0.0, 0.0, -10.0);
+    let far = vec3(0.0, 0.0, -45.0);
+    sim.spawn_units(&[near, far]);
+    sim.step(2);
+    // Just after a fixed step, at 4 frames a step the events are gone before the next one
+    sim.player_fired();
+    sim.step_frames(8, 4);
+    assert!(sim.unit_spawned_at(near).last_known_target.is_some());
+    assert!(sim.unit_spawned_at(far).last_known_target.is_none());
+
+    sim.step(1);
+    sim.damage_units(0.1, DamageKind::Bullet);
+    sim.step_frames(8, 4);
+    assert!(sim.unit_spawned_at(far).last_known_target.is_some());
+}
+
+#[test]
+fn units_that_spot_the_player_alert_units_nearby() {
+    let mut sim = Sim::start();
+    // Hides the second unit from the player
+    sim.spawn_wall(vec3(0.0, 2.0, 15.0), vec3(5.0, 2.0, 0.2));
+    let spotter = vec3(0.0, 0.0, 10.0);
+    let hidden = vec3(0.0, 0.0, 20.0);
+    sim.spawn_units(&[spotter, hidden]);
+    let alerted = sim.run_until(20.0, |sim| {
+        sim.unit_spawned_at(hidden).last_known_target.is_some()
+    });
+    assert!(alerted.is_some(), "hidden unit was never alerted");
+}
+
+#[test]
+fn hurt_units_retreat() {
+    let mut sim = Sim::start();
+    let hurt = vec3(6.0, 0.0, 10.0);
+    let healthy = vec3(-6.0, 0.0, 10.0);
+    sim.spawn_named_units(&[
+        ("enemyspawn.sniper", hurt),
+        ("enemyspawn.sniper.001", healthy),
+    ]);
+    sim.step(2);
+    sim.set_unit_health(hurt, 0.2);
+    // So they come and find the player
+    sim.player_fired();
+    let actions = sim.unit_actions(20.0, &[hurt, healthy]);
+    assert!(
+        actions[0].contains(&UnitAction::Retreat),
+        "hurt unit never retreated: {:?}",
+        actions[0]
+    );
+    assert!(
+        !actions[1].contains(&UnitAction::Retreat),
+        "healthy unit retreated: {:?}",
+        actions[1]
+    );
+}
+
+#[test]
+fn units_investigate_where_they_last_saw_the_player() {
+    let mut sim = Sim::start();
+    let spawn = vec3(0.0, 0.0, 10.0);
+    sim.spawn_units(&[spawn]);
+    let spotted = sim.run_until(10.0, |sim| {
+        sim.unit_spawned_at(spawn).target_to_shoot.is_some()
+    });
+    assert!(spotted.is_some(), "unit never spotted the player");
+    let seen_at = sim.unit_spawned_at(spawn).last_known_target.unwrap();
+
+    // Sneaks off behind a wall
+    sim.spawn_wall(vec3(0.0, 2.0, 5.0), vec3(5.0, 2.0, 0.2));
+    let player = sim.player_position();
+    sim.set_player_position(player + vec3(3.0, 0.0, 0.0));
+    let investigating = sim.run_until(5.0, |sim| {
+        sim.unit_spawned_at(spawn).action == UnitAction::Investigate
+    });
+    assert!(investigating.is_some(), "unit didn't investigate");
+    let unit = sim.unit_spawned_at(spawn);
+    assert_eq!(unit.last_known_target, Some(seen_at));
+    let dest = *unit.path.last().expect("unit should be walking");
+    assert!(
+        dest.xz().distance(seen_at.xz()) < 0.1,
+        "unit went to {dest} instead of {seen_at}"
+    );
+}
+
+#[test]
+fn unit_kinds_weigh_actions_differently() {
+    let mut sim = Sim::start();
+    let swarmer = vec3(6.0, 0.0, 10.0);
+    let sniper = vec3(-6.0, 0.0, 10.0);
+    sim.spawn_named_units(&[
+        ("enemyspawn.swarmer", swarmer),
+        ("enemyspawn.sniper", sniper),
+    ]);
+    sim.step(2);
+    sim.player_fired();
+    let actions = sim.unit_actions(20.0, &[swarmer, sniper]);
+    let moves_around = |actions: &[UnitAction]| {
+        actions
+            .iter()
+            .any(|action| matches!(action, UnitAction::Strafe | UnitAction::Flank))
+    };
+    // Swarmers weigh strafing and flanking highly, snipers not at all
+    assert!(
+        moves_around(&actions[0]),
+        "swarmer never strafed or flanked: {:?}",
+        actions[0]
+    );
+    assert!(
+        !moves_around(&actions[1]),
+        "sniper strafed or flanked: {:?}",
+        actions[1]
+    );
+}
+
+#[test]
+fn projectiles_damage_once_and_despawn() {
+    let mut sim = Sim::start();
+    // At chest height, aimed at the feet it would hit the floor first
+    let player = sim.player_position() + vec3(0.0, 0.6, 0.0);
+    sim.spawn_projectile(player + vec3(0.0, 0.0, 10.0), player, 100.0, 0.25);
+    sim.step(60);
+    assert_eq!(sim.player_health(), 0.75);
+    assert_eq!(sim.projectile_count(), 0);
+}
+
+#[test]
+fn projectile_hits_punch_the_view() {
+    let mut sim = Sim::start();
+    let player = sim.player_position() + vec3(0.0, 0.6, 0.0);
+    let before = sim.view_angles();
+    sim.spawn_projectile(player + vec3(0.0, 0.0, 10.0), player, 100.0, 0.25);
+    sim.step(60);
+    let after = sim.view_angles();
+    assert!(after.x > before.x, "view wasn't knocked up");
+    assert_ne!(after.y, before.y, "view wasn't knocked sideways");
+}
+
+#[test]
+fn fast_projectiles_dont_pass_through_walls() {
+    let mut sim = Sim::start();
+    let player = sim.player_position();
+    sim.spawn_wall(player + vec3(0.0, 0.0, 5.0), vec3(5.0, 5.0, 0.05));
+    sim.spawn_projectile(player + vec3(0.0, 0.0, 10.0), player, 2000.0, 0.25);
+    sim.step(60);
+    assert_eq!(sim.player_health(), 1.0);
+    assert_eq!(sim.projectile_count(), 0);
+}
+
+#[test]
+fn armor_reduces_damage_and_dead_units_blow_up() {
+    let mut sim = Sim::start();
+    sim.spawn_named_units(&[
+        ("enemyspawn", vec3(8.0, 0.0, 0.0)),
+        ("enemyspawn.heavy", vec3(-8.0, 0.0, 0.0)),
+    ]);
+    sim.step(2);
+    sim.damage_units(1.05, DamageKind::Bullet);
+    sim.step(2);
+    // The drone has no armor, the heavy takes off 0.05 then resists 30% of bullets
+    let healths = sim.unit_healths();
+    assert_eq!(healths.len(), 1);
+    assert!((healths[0] - 2.3).abs() < 0.001, "heavy has {}", healths[0]);
+}
+
+#[test]
+fn number_keys_switch_weapons() {
+    let mut sim = Sim::start();
+    sim.step(2);
+    assert_eq!(sim.weapon_name(), "rifle");
+    sim.press_key(KeyCode::Key2);
+    assert_eq!(sim.weapon_name(), "marksman");
+    sim.press_key(KeyCode::Q);
+    assert_eq!(sim.weapon_name(), "smg");
+}
+
+#[test]
+fn reloading_takes_time_and_fills_from_reserve() {
+    let mut sim = Sim::start();
+    sim.step(2);
+    sim.set_ammo(5, 10);
+    sim.press_key(KeyCode::R);
+    sim.step(30);
+    assert_eq!(sim.ammo(), (5, 10), "reload finished early");
+    let took = sim.run_until(3.0, |sim| sim.ammo().0 > 5);
+    assert!(took.is_some(), "reload never finished");
+    assert_eq!(sim.ammo(), (15, 0));
+}
+
+#[test]
+fn pickups_are_only_used_when_needed() {
+    let mut sim = Sim::start();
+    sim.step(2);
+    let position = sim.player_position();
+    sim.spawn_pickup("pickup.health", position);
+    sim.step(10);
+    assert_eq!(sim.pickup_count(), 1, "health pickup used at full health");
+    // Still standing in it
+    sim.set_player_health(0.5);
+    sim.step(2);
+    assert_eq!(
+        sim.pickup_count(),
+        0,
+        "health pickup wasn't used once needed"
+    );
+    assert_eq!(sim.player_health(), 0.75);
+
+    sim.set_ammo(30, 0);
+    sim.spawn_pickup("pickup.ammo.001", position);
+    sim.step(10);
+    assert_eq!(sim.ammo(), (30, 30));
+    assert_eq!(sim.pickup_count(), 0);
+}
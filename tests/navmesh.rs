@@ -0,0 +1,113 @@
+use bevy::math::{vec3, Vec3};
+use traverse::navmesh::NavMesh;
+
+/// Two triangles covering a flat square, facing up
+fn square(x0: f32, z0: f32, x1: f32, z1: f32) -> [[Vec3; 3]; 2] {
+    [
+        [vec3(x0, 0.0, z0), vec3(x0, 0.0, z1), vec3(x1, 0.0, z1)],
+        [vec3(x0, 0.0, z0), vec3(x1, 0.0, z1), vec3(x1, 0.0, z0)],
+    ]
+}
+
+/// Three squares in an L, the corner at (1, 1) is the inside of the bend
+fn l_shape() -> NavMesh {
+    NavMesh::from_triangles(
+        [
+            square(0.0, 0.0, 1.0, 1.0),
+            square(1.0, 0.0, 2.0, 1.0),
+            square(1.0, 1.0, 2.0, 2.0),
+        ]
+        .into_iter()
+        .flatten(),
+    )
+}
+
+fn assert_near(a: Vec3, b: Vec3) {
+    assert!(a.distance(b) < 0.001, "{a} is not {b}");
+}
+
+#[test]
+fn paths_go_around_corners() {
+    let navmesh = l_shape();
+    let path = navmesh
+        .find_path(vec3(0.5, 0.0, 0.2), vec3(1.2, 0.0, 1.8))
+        .expect("should find a path");
+    assert_eq!(path.len(), 2);
+    assert_near(path[0], vec3(1.0, 0.0, 1.0));
+    assert_near(path[1], vec3(1.2, 0.0, 1.8));
+}
+
+#[test]
+fn paths_in_the_open_are_straight() {
+    let navmesh = l_shape();
+    let path = navmesh
+        .find_path(vec3(0.2, 0.0, 0.5), vec3(1.8, 0.0, 0.5))
+        .expect("should find a path");
+    assert_eq!(path.len(), 1);
+    assert_near(path[0], vec3(1.8, 0.0, 0.5));
+}
+
+#[test]
+fn unconnected_areas_have_no_path() {
+    let navmesh = NavMesh::from_triangles(
+        [square(0.0, 0.0, 1.0, 1.0), square(3.0, 0.0, 4.0, 1.0)]
+            .into_iter()
+            .flatten(),
+    );
+    assert!(navmesh
+        .find_path(vec3(0.5, 0.0, 0.5), vec3(3.5, 0.0, 0.5))
+        .is_none());
+}
+
+#[test]
+fn walls_are_not_walkable() {
+    let navmesh = NavMesh::from_triangles([[
+        vec3(0.0, 0.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        vec3(1.0, 0.0, 0.0),
+    ]]);
+    assert!(navmesh.is_empty());
+}
+
+/// The faces of a box, facing out
+fn cuboid(center: Vec3, half: Vec3) -> Vec<[Vec3; 3]> {
+    [
+        (Vec3::Y, Vec3::Z, Vec3::X),
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+        (Vec3::X, Vec3::Y, Vec3::Z),
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y, Vec3::X),
+    ]
+    .into_iter()
+    .flat_map(|(normal, u, v)| {
+        let (face, u, v) = (center + normal * half, u * half, v * half);
+        [
+            [face - u - v, face + u - v, face + u + v],
+            [face - u - v, face + u + v, face - u + v],
+        ]
+    })
+    .collect()
+}
+
+#[test]
+fn paths_keep_clear_of_obstacles() {
+    let floor = square(-10.0, -10.0, 10.0, 10.0);
+    let obstacle = cuboid(vec3(0.0, 0.5, 0.0), vec3(1.0, 0.5, 1.0));
+    let navmesh = NavMesh::from_geometry(floor.into_iter().chain(obstacle));
+    let start = vec3(-5.0, 0.0, 0.2);
+    let goal = vec3(5.0, 0.0, 0.2);
+    let path = navmesh.find_path(start, goal).expect("should find a path");
+    assert!(path.len() > 1, "path went straight through: {path:?}");
+    assert_near(*path.last().unwrap(), goal);
+    // The floor under the box is cut out, and what's left is shrunk away from it
+    let mut from = start;
+    for to in path {
+        for step in 0..=100 {
+            let point = from.lerp(to, step as f32 / 100.0);
+            let outside = vec3(point.x.abs() - 1.0, 0.0, point.z.abs() - 1.0).max(Vec3::ZERO);
+            assert!(outside.length() >= 0.3, "path goes past the box at {point}");
+        }
+        from = to;
+    }
+}
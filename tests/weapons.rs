@@ -0,0 +1,49 @@
+use traverse::weapons::WeaponManifest;
+
+fn manifest() -> WeaponManifest {
+    serde_json::from_str(include_str!("../assets/weapons/manifest.weapons.json"))
+        .expect("weapons should parse")
+}
+
+#[test]
+fn loadout_weapons_are_defined() {
+    let manifest = manifest();
+    for name in &manifest.loadout {
+        assert!(manifest.get(name).is_some(), "{name} isn't defined");
+    }
+}
+
+#[test]
+fn falloff_is_interpolated_and_clamped() {
+    let manifest = manifest();
+    let rifle = manifest.get("rifle").expect("rifle should be defined");
+    assert_eq!(rifle.falloff(0.0), 1.0);
+    assert_eq!(rifle.falloff(36.0), 1.0);
+    assert!((rifle.falloff(37.5) - 0.75).abs() < 0.001);
+    assert_eq!(rifle.falloff(1000.0), 0.1);
+}
+
+#[test]
+fn spread_grows_with_bloom_and_speed() {
+    let manifest = manifest();
+    for weapon in &manifest.weapons {
+        let still = weapon.spread_at(0.0, 0.0);
+        assert_eq!(still, weapon.spread);
+        assert!(
+            weapon.spread_at(weapon.bloom, 0.0) > still,
+            "{} doesn't bloom",
+            weapon.name
+        );
+        assert!(
+            weapon.spread_at(0.0, 8.0) > still,
+            "{} is as accurate moving",
+            weapon.name
+        );
+        assert_eq!(
+            weapon.spread_at(1000.0, 0.0),
+            weapon.spread + weapon.max_bloom,
+            "{} blooms past its max",
+            weapon.name
+        );
+    }
+}